@@ -0,0 +1,311 @@
+use sdl2::render::WindowCanvas;
+use sdl2::gfx::primitives::DrawRenderer;
+
+use crate::environment::*;
+use crate::network::*;
+
+// Constants from the double pole benchmark used in the NEAT paper
+const GRAVITY: f32 = -9.8;
+const CART_MASS: f32 = 1.0;
+const FORCE_MAGNITUDE: f32 = 10.0;
+const POLE_FRICTION: f32 = 0.000002;
+const TRACK_LIMIT: f32 = 2.4;
+const TIME_STEP: f32 = 0.01;
+// Integration steps per network activation
+const STEPS_PER_ACTION: u32 = 2;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pole {
+    pub mass: f32,
+    pub half_length: f32,
+    pub angle: f32,
+    pub angular_velocity: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CartPoleConfig {
+    pub double_pole: bool,
+    // Without velocities the network only sees positions and angles
+    pub velocity_inputs: bool,
+    pub max_steps: u32,
+}
+
+impl CartPoleConfig {
+    pub fn single_pole(velocity_inputs: bool) -> Self {
+        CartPoleConfig {
+            double_pole: false,
+            velocity_inputs,
+            max_steps: 10000,
+        }
+    }
+
+    pub fn double_pole(velocity_inputs: bool) -> Self {
+        CartPoleConfig {
+            double_pole: true,
+            velocity_inputs,
+            max_steps: 10000,
+        }
+    }
+
+    // Number of network inputs, including the bias input
+    pub fn input_count(&self) -> u32 {
+        let per_body = if self.velocity_inputs { 2 } else { 1 };
+        let bodies = if self.double_pole { 3 } else { 2 };
+        per_body * bodies + 1
+    }
+
+    pub fn failure_angle(&self) -> f32 {
+        if self.double_pole {
+            36f32.to_radians()
+        } else {
+            12f32.to_radians()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CartPole {
+    pub config: CartPoleConfig,
+    pub position: f32,
+    pub velocity: f32,
+    pub poles: Vec<Pole>,
+    pub steps: u32,
+}
+
+impl CartPole {
+    pub fn new(config: CartPoleConfig) -> Self {
+        let mut poles = vec!(Pole { mass: 0.1, half_length: 0.5, angle: 4f32.to_radians(), angular_velocity: 0.0 });
+        if config.double_pole {
+            poles.push(Pole { mass: 0.01, half_length: 0.05, angle: 0.0, angular_velocity: 0.0 });
+        }
+
+        CartPole {
+            config,
+            position: 0.0,
+            velocity: 0.0,
+            poles,
+            steps: 0,
+        }
+    }
+
+    // Scaled observation followed by the bias input
+    pub fn inputs(&self) -> Vec<f32> {
+        let mut inputs = vec!(self.position / TRACK_LIMIT);
+        if self.config.velocity_inputs {
+            inputs.push(self.velocity / 2.0);
+        }
+
+        for pole in &self.poles {
+            inputs.push(pole.angle / 0.52);
+            if self.config.velocity_inputs {
+                inputs.push(pole.angular_velocity / 2.0);
+            }
+        }
+
+        inputs.push(1.0);
+        inputs
+    }
+
+    pub fn failed(&self) -> bool {
+        self.position.abs() > TRACK_LIMIT
+            || self.poles.iter().any(|pole| pole.angle.abs() > self.config.failure_angle())
+    }
+
+    pub fn done(&self) -> bool {
+        self.failed() || self.steps >= self.config.max_steps
+    }
+
+    // Behaviour descriptor for novelty search: where the cart and the first pole are
+    pub fn behavior(&self) -> Vec<f32> {
+        vec!(self.position / TRACK_LIMIT, self.poles[0].angle / self.config.failure_angle())
+    }
+
+    // Applies an action in [-1, 1] as a force on the cart
+    pub fn step(&mut self, action: f32) {
+        let force = action.clamp(-1.0, 1.0) * FORCE_MAGNITUDE;
+
+        for _ in 0..STEPS_PER_ACTION {
+            self.integrate(force);
+        }
+        self.steps += 1;
+    }
+
+    // State layout: [x, x', angle_1, angle_1', angle_2, angle_2', ...]
+    fn state(&self) -> Vec<f32> {
+        let mut state = vec!(self.position, self.velocity);
+        for pole in &self.poles {
+            state.push(pole.angle);
+            state.push(pole.angular_velocity);
+        }
+        state
+    }
+
+    fn derivatives(&self, state: &[f32], force: f32) -> Vec<f32> {
+        let mut forces = force;
+        let mut masses = CART_MASS;
+
+        for (i, pole) in self.poles.iter().enumerate() {
+            let angle = state[2 + i * 2];
+            let angular_velocity = state[3 + i * 2];
+            let ml = pole.half_length * pole.mass;
+            let friction = POLE_FRICTION * angular_velocity / ml;
+
+            forces += ml * angular_velocity * angular_velocity * angle.sin()
+                + 0.75 * pole.mass * angle.cos() * (friction + GRAVITY * angle.sin());
+            masses += pole.mass * (1.0 - 0.75 * angle.cos() * angle.cos());
+        }
+
+        let acceleration = forces / masses;
+        let mut derivatives = vec!(state[1], acceleration);
+
+        for (i, pole) in self.poles.iter().enumerate() {
+            let angle = state[2 + i * 2];
+            let angular_velocity = state[3 + i * 2];
+            let friction = POLE_FRICTION * angular_velocity / (pole.half_length * pole.mass);
+
+            derivatives.push(angular_velocity);
+            derivatives.push(-0.75 * (acceleration * angle.cos() + GRAVITY * angle.sin() + friction) / pole.half_length);
+        }
+
+        derivatives
+    }
+
+    // Fourth order Runge-Kutta step
+    fn integrate(&mut self, force: f32) {
+        let state = self.state();
+        let offset = |s: &[f32], d: &[f32], scale: f32| -> Vec<f32> {
+            s.iter().zip(d).map(|(s, d)| s + d * scale).collect()
+        };
+
+        let k1 = self.derivatives(&state, force);
+        let k2 = self.derivatives(&offset(&state, &k1, TIME_STEP / 2.0), force);
+        let k3 = self.derivatives(&offset(&state, &k2, TIME_STEP / 2.0), force);
+        let k4 = self.derivatives(&offset(&state, &k3, TIME_STEP), force);
+
+        let next: Vec<f32> = (0..state.len())
+            .map(|i| state[i] + TIME_STEP / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]))
+            .collect();
+
+        self.position = next[0];
+        self.velocity = next[1];
+        for (i, pole) in self.poles.iter_mut().enumerate() {
+            pole.angle = next[2 + i * 2];
+            pole.angular_velocity = next[3 + i * 2];
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut WindowCanvas, x: f32, y: f32, width: f32, height: f32) {
+        let scale = width / (TRACK_LIMIT * 2.0 + 0.5);
+        let center_x = x + width / 2.0;
+        let track_y = y + height * 0.75;
+
+        // Track and limits
+        canvas.hline((center_x - TRACK_LIMIT * scale) as i16, (center_x + TRACK_LIMIT * scale) as i16, track_y as i16, (120, 120, 120, 255))
+            .expect("Failed to draw track");
+        for limit in &[-TRACK_LIMIT, TRACK_LIMIT] {
+            canvas.vline((center_x + limit * scale) as i16, (track_y - 20.0) as i16, (track_y + 20.0) as i16, (255, 0, 0, 255))
+                .expect("Failed to draw track limit");
+        }
+
+        // Cart
+        let cart_x = center_x + self.position * scale;
+        let cart_width = 0.4 * scale;
+        let cart_height = 0.2 * scale;
+        canvas.box_((cart_x - cart_width / 2.0) as i16, (track_y - cart_height) as i16, (cart_x + cart_width / 2.0) as i16, track_y as i16, (200, 200, 200, 255))
+            .expect("Failed to draw cart");
+
+        // Poles, drawn at full length from the top of the cart
+        let pole_colors: [(u8, u8, u8, u8); 2] = [(255, 100, 0, 255), (0, 200, 255, 255)];
+        for (i, pole) in self.poles.iter().enumerate() {
+            let length = pole.half_length * 2.0 * scale;
+            let base_y = track_y - cart_height;
+            let tip_x = cart_x + pole.angle.sin() * length;
+            let tip_y = base_y - pole.angle.cos() * length;
+            canvas.thick_line(cart_x as i16, base_y as i16, tip_x as i16, tip_y as i16, 4, pole_colors[i % 2])
+                .expect("Failed to draw pole");
+        }
+    }
+}
+
+// Rewards one point for every step all poles stay up
+impl Environment for CartPole {
+    fn reset(&mut self) {
+        *self = CartPole::new(self.config);
+    }
+
+    fn observe(&self) -> Vec<f32> {
+        self.inputs()
+    }
+
+    fn step(&mut self, actions: &[f32]) -> f32 {
+        CartPole::step(self, actions[0]);
+        1.0
+    }
+
+    fn done(&self) -> bool {
+        CartPole::done(self)
+    }
+}
+
+// Fraction of the step limit the network keeps every pole up. The variants
+// without velocity inputs use this fitness too, not the damping fitness of
+// Gruau et al. that the NEAT paper uses for them (which also rewards keeping
+// the cart and poles still), and no generalisation test over many start states
+// is run, so their results are not directly comparable with the paper's.
+pub fn evaluate_cartpole(n: &Network, config: CartPoleConfig) -> f32 {
+    let episode_config = EpisodeConfig { episodes: 1, max_steps: config.max_steps };
+    evaluate_episodes(n, &mut CartPole::new(config), episode_config) / config.max_steps as f32
+}
+
+// Same fitness as evaluate_cartpole, along with the behaviour at the end of the episode
+pub fn evaluate_cartpole_behavior(n: &Network, config: CartPoleConfig) -> (f32, Vec<f32>) {
+    let mut cartpole = CartPole::new(config);
+    let steps = run_episode(n, &mut cartpole, config.max_steps);
+    (steps / config.max_steps as f32, cartpole.behavior())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uncontrolled_pole_falls() {
+        let mut cartpole = CartPole::new(CartPoleConfig::single_pole(true));
+        let start_angle = cartpole.poles[0].angle;
+
+        while !cartpole.done() {
+            cartpole.step(0.0);
+        }
+
+        // The pole starts leaning to one side and keeps falling that way
+        assert!(cartpole.failed());
+        assert!(cartpole.steps < 1000);
+        assert!(cartpole.poles[0].angle > start_angle);
+        assert!(cartpole.poles[0].angle > cartpole.config.failure_angle());
+    }
+
+    #[test]
+    fn centred_upright_state_stays_put() {
+        for config in &[CartPoleConfig::single_pole(true), CartPoleConfig::double_pole(true)] {
+            let mut cartpole = CartPole::new(*config);
+            for pole in &mut cartpole.poles {
+                pole.angle = 0.0;
+            }
+
+            cartpole.step(0.0);
+            assert_eq!(cartpole.steps, 1);
+            assert_eq!((cartpole.position, cartpole.velocity), (0.0, 0.0));
+            assert!(cartpole.poles.iter().all(|pole| pole.angle == 0.0 && pole.angular_velocity == 0.0));
+        }
+    }
+
+    #[test]
+    fn force_pushes_the_cart_and_tips_the_poles_back() {
+        let mut cartpole = CartPole::new(CartPoleConfig::single_pole(true));
+        cartpole.poles[0].angle = 0.0;
+
+        cartpole.step(1.0);
+        assert!(cartpole.velocity > 0.0);
+        assert!(cartpole.poles[0].angular_velocity < 0.0);
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs;
+use std::io;
+
+use crate::genealogy::*;
+use crate::hall_of_fame::*;
+use crate::neat::*;
+use crate::network::*;
+
+impl NEAT {
+    // A `checkpoint <generation> <innovation> <next network id> <next species id>`
+    // line, each population genome after a `genome` line, then the hall of fame.
+    // Species are not saved; the population regroups on the next generation.
+    pub fn to_checkpoint_text(&self) -> String {
+        let mut output = format!("checkpoint {} {} {} {}\n", self.generation, self.global_inno_number, self.next_network_id, self.next_species_id);
+        for network in &self.pop {
+            output += "genome\n";
+            output += network.to_text().as_str();
+        }
+        output += self.hall_of_fame.to_text().as_str();
+        output
+    }
+
+    pub fn from_checkpoint_text(text: &str) -> io::Result<NEAT> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid checkpoint line: {}", line));
+
+        let (population_text, hall_of_fame_text) = match text.find("hall_of_fame") {
+            Some(index) => text.split_at(index),
+            None => return Err(invalid("missing hall_of_fame")),
+        };
+
+        let mut lines = population_text.lines().filter(|line| line.trim().len() != 0);
+        let header = lines.next().unwrap_or("");
+        let counters: Vec<&str> = header.split_whitespace().collect();
+        if counters.len() != 5 || counters[0] != "checkpoint" {
+            return Err(invalid(header));
+        }
+
+        let mut genomes: Vec<String> = Vec::new();
+        for line in lines {
+            if line.trim() == "genome" {
+                genomes.push(String::new());
+            } else {
+                match genomes.last_mut() {
+                    Some(genome) => {
+                        *genome += line;
+                        *genome += "\n";
+                    },
+                    None => return Err(invalid(line)),
+                }
+            }
+        }
+
+        let pop = genomes.iter().map(|genome| Network::from_text(genome)).collect::<io::Result<Vec<Network>>>()?;
+        let (input_count, output_count) = match pop.first() {
+            Some(n) => (n.input_count, n.output_count),
+            None => return Err(invalid("empty population")),
+        };
+        if pop.iter().any(|n| n.input_count != input_count || n.output_count != output_count) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Checkpoint genomes have different input or output counts"));
+        }
+        if pop.len() < NEAT::ELITE_COUNT {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Checkpoint has {} genomes but training needs at least {}", pop.len(), NEAT::ELITE_COUNT)));
+        }
+
+        let mut neat = NEAT::new(pop.len() as u32, input_count, output_count);
+        neat.generation = counters[1].parse().map_err(|_| invalid(header))?;
+        neat.global_inno_number = counters[2].parse().map_err(|_| invalid(header))?;
+        neat.next_network_id = counters[3].parse().map_err(|_| invalid(header))?;
+        neat.next_species_id = counters[4].parse().map_err(|_| invalid(header))?;
+        neat.pop = pop;
+        // Lineage is not saved, so loaded networks are the roots of their ancestry
+        neat.genealogy = Genealogy::new();
+        for n in &neat.pop {
+            neat.genealogy.record(n);
+        }
+        // The whole population starts in one fresh species
+        neat.species_info[0].id = neat.next_species_id;
+        neat.next_species_id += 1;
+        neat.hall_of_fame = HallOfFame::from_text(hall_of_fame_text)?;
+
+        Ok(neat)
+    }
+
+    pub fn save_checkpoint(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_checkpoint_text())
+    }
+
+    pub fn load_checkpoint(path: &str) -> io::Result<NEAT> {
+        NEAT::from_checkpoint_text(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip_preserves_training_state() {
+        let mut neat = NEAT::new(30, 3, 1);
+        neat.add_connection_chance = 0.5;
+        neat.add_node_chance = 0.2;
+        for _ in 0..5 {
+            neat.train();
+        }
+
+        let text = neat.to_checkpoint_text();
+        let loaded = NEAT::from_checkpoint_text(&text).unwrap();
+
+        assert_eq!(loaded.generation, neat.generation);
+        assert_eq!(loaded.global_inno_number, neat.global_inno_number);
+        assert_eq!(loaded.next_network_id, neat.next_network_id);
+        assert_eq!(loaded.pop_size, neat.pop_size);
+        assert_eq!(loaded.hall_of_fame.len(), neat.hall_of_fame.len());
+        for (a, b) in loaded.pop.iter().zip(&neat.pop) {
+            assert_eq!(a.to_text(), b.to_text());
+        }
+
+        // Loading gives the population a fresh species, which is the only change
+        assert_eq!(loaded.species_info[0].id, neat.next_species_id);
+        assert_eq!(loaded.next_species_id, neat.next_species_id + 1);
+    }
+
+    #[test]
+    fn populations_smaller_than_the_elite_are_rejected() {
+        let text = NEAT::new(NEAT::ELITE_COUNT as u32 - 1, 3, 1).to_checkpoint_text();
+        assert!(NEAT::from_checkpoint_text(&text).is_err());
+        assert!(NEAT::from_checkpoint_text(&NEAT::new(NEAT::ELITE_COUNT as u32, 3, 1).to_checkpoint_text()).is_ok());
+    }
+}
@@ -0,0 +1,359 @@
+use std::fs;
+use std::io;
+
+use crate::neat::compare_scores;
+use crate::network::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    None,
+    // Scale each column to [0, 1]
+    MinMax,
+    // Shift each column to zero mean and unit standard deviation
+    ZScore,
+}
+
+impl Normalization {
+    pub fn from_name(name: &str) -> Option<Normalization> {
+        match name {
+            "none" => Some(Normalization::None),
+            "minmax" => Some(Normalization::MinMax),
+            "zscore" => Some(Normalization::ZScore),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    // Columns are chosen by name from the header row
+    pub input_columns: Vec<String>,
+    pub target_columns: Vec<String>,
+    pub normalize_inputs: Normalization,
+    pub normalize_targets: Normalization,
+}
+
+// Rows of inputs and targets for supervised learning. The network sees each row's
+// inputs followed by a bias input, the same convention evaluate_xor uses.
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub inputs: Vec<Vec<f32>>,
+    pub targets: Vec<Vec<f32>>,
+}
+
+impl Dataset {
+    pub fn load_csv(path: &str, options: &CsvOptions) -> io::Result<Dataset> {
+        Dataset::from_csv(&fs::read_to_string(path)?, options)
+    }
+
+    pub fn from_csv(text: &str, options: &CsvOptions) -> io::Result<Dataset> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut lines = text.lines().filter(|line| line.trim().len() != 0);
+        let header: Vec<&str> = lines.next()
+            .ok_or(invalid("CSV file is empty".to_string()))?
+            .split(',')
+            .map(|field| field.trim())
+            .collect();
+
+        let find_columns = |names: &Vec<String>| -> io::Result<Vec<usize>> {
+            names.iter()
+                .map(|name| header.iter().position(|h| h == name).ok_or(invalid(format!("No column named {}", name))))
+                .collect()
+        };
+        let input_indexes = find_columns(&options.input_columns)?;
+        let target_indexes = find_columns(&options.target_columns)?;
+        if target_indexes.len() == 0 {
+            return Err(invalid("At least one target column is needed".to_string()));
+        }
+
+        let mut dataset = Dataset {
+            inputs: Vec::new(),
+            targets: Vec::new(),
+        };
+
+        for (row, line) in lines.enumerate() {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            let parse = |indexes: &Vec<usize>| -> io::Result<Vec<f32>> {
+                indexes.iter()
+                    .map(|&i| {
+                        fields.get(i)
+                            .and_then(|field| field.parse::<f32>().ok())
+                            .filter(|value| value.is_finite())
+                            .ok_or(invalid(format!("Row {} column {} is not a finite number", row + 1, header[i])))
+                    })
+                    .collect()
+            };
+
+            dataset.inputs.push(parse(&input_indexes)?);
+            dataset.targets.push(parse(&target_indexes)?);
+        }
+
+        normalize(&mut dataset.inputs, options.normalize_inputs);
+        normalize(&mut dataset.targets, options.normalize_targets);
+
+        Ok(dataset)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    // Pass to NEAT::new as num_inputs; includes the bias input
+    pub fn input_count(&self) -> u32 {
+        self.inputs.first().map_or(0, |row| row.len() as u32) + 1
+    }
+
+    // Pass to NEAT::new as num_outputs for regression
+    pub fn output_count(&self) -> u32 {
+        self.targets.first().map_or(0, |row| row.len() as u32)
+    }
+
+    // Largest number of classes a classification dataset may have, each of which gets an output
+    pub const MAX_CLASSES: u32 = 1000;
+
+    // Checks that the first target column holds class indexes 0, 1, 2, ... below MAX_CLASSES
+    pub fn check_classes(&self) -> io::Result<()> {
+        for (row, targets) in self.targets.iter().enumerate() {
+            let label = targets[0];
+            if label < 0.0 || label.fract() != 0.0 || label >= Dataset::MAX_CLASSES as f32 {
+                let message = format!("Row {} target {} is not a class index below {}", row + 1, label, Dataset::MAX_CLASSES);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Pass to NEAT::new as num_outputs for classification, where the first
+    // target column holds class indexes 0, 1, 2, ... as checked by check_classes
+    pub fn class_count(&self) -> u32 {
+        self.targets.iter().map(|row| row[0] as u32 + 1).max().unwrap_or(0)
+    }
+
+    pub fn predict(&self, n: &Network, row: usize) -> Vec<f32> {
+        let mut inputs = self.inputs[row].clone();
+        inputs.push(1.0);
+        n.evaluate(inputs)
+    }
+}
+
+fn normalize(rows: &mut [Vec<f32>], normalization: Normalization) {
+    if normalization == Normalization::None || rows.len() == 0 {
+        return;
+    }
+
+    for column in 0..rows[0].len() {
+        let values: Vec<f32> = rows.iter().map(|row| row[column]).collect();
+        let count = values.len() as f32;
+
+        let (offset, scale) = match normalization {
+            Normalization::MinMax => {
+                let min = values.iter().cloned().fold(std::f32::MAX, f32::min);
+                let max = values.iter().cloned().fold(std::f32::MIN, f32::max);
+                (min, max - min)
+            },
+            Normalization::ZScore => {
+                let mean = values.iter().sum::<f32>() / count;
+                let variance = values.iter().map(|v| (v - mean).powf(2.0)).sum::<f32>() / count;
+                (mean, variance.sqrt())
+            },
+            Normalization::None => (0.0, 1.0),
+        };
+
+        // Constant columns would divide by zero
+        let scale = if scale.abs() < std::f32::EPSILON { 1.0 } else { scale };
+        for row in rows.iter_mut() {
+            row[column] = (row[column] - offset) / scale;
+        }
+    }
+}
+
+// Regression fitness: 1 / (1 + mean squared error) over every target
+pub fn evaluate_mse(n: &Network, data: &Dataset) -> f32 {
+    let mut error = 0.0;
+    let mut count = 0;
+
+    for row in 0..data.len() {
+        let outputs = data.predict(n, row);
+        for (output, target) in outputs.iter().zip(&data.targets[row]) {
+            error += (target - output).powf(2.0);
+            count += 1;
+        }
+    }
+
+    1.0 / (1.0 + error / count.max(1) as f32)
+}
+
+// Classification fitness: fraction of rows whose largest output is the target class
+pub fn evaluate_accuracy(n: &Network, data: &Dataset) -> f32 {
+    let mut correct = 0;
+
+    for row in 0..data.len() {
+        let outputs = data.predict(n, row);
+        let predicted = (0..outputs.len())
+            .max_by(|&a, &b| compare_scores(outputs[a], outputs[b]))
+            .unwrap_or(0);
+
+        if predicted == data.targets[row][0] as usize {
+            correct += 1;
+        }
+    }
+
+    correct as f32 / data.len().max(1) as f32
+}
+
+// Classification fitness: 1 / (1 + mean cross-entropy of the softmaxed outputs)
+pub fn evaluate_cross_entropy(n: &Network, data: &Dataset) -> f32 {
+    let mut loss = 0.0;
+
+    for row in 0..data.len() {
+        let outputs = data.predict(n, row);
+        let exp_sum: f32 = outputs.iter().map(|o| o.exp()).sum();
+        let target = data.targets[row][0] as usize;
+        let probability = outputs.get(target).map_or(0.0, |o| o.exp() / exp_sum);
+
+        loss -= probability.max(1e-7).ln();
+    }
+
+    1.0 / (1.0 + loss / data.len().max(1) as f32)
+}
+
+// How networks are scored against a dataset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Mse,
+    Accuracy,
+    CrossEntropy,
+}
+
+impl Metric {
+    pub fn from_name(name: &str) -> Option<Metric> {
+        match name {
+            "mse" => Some(Metric::Mse),
+            "accuracy" => Some(Metric::Accuracy),
+            "cross-entropy" => Some(Metric::CrossEntropy),
+            _ => None,
+        }
+    }
+
+    // Classification metrics need class indexes as targets
+    pub fn check(&self, data: &Dataset) -> io::Result<()> {
+        match self {
+            Metric::Mse => Ok(()),
+            Metric::Accuracy | Metric::CrossEntropy => data.check_classes(),
+        }
+    }
+
+    // Regression needs an output per target column, classification one per class
+    pub fn output_count(&self, data: &Dataset) -> u32 {
+        match self {
+            Metric::Mse => data.output_count(),
+            Metric::Accuracy | Metric::CrossEntropy => data.class_count(),
+        }
+    }
+
+    pub fn fitness(&self, n: &Network, data: &Dataset) -> f32 {
+        match self {
+            Metric::Mse => evaluate_mse(n, data),
+            Metric::Accuracy => evaluate_accuracy(n, data),
+            Metric::CrossEntropy => evaluate_cross_entropy(n, data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(normalize_inputs: Normalization) -> CsvOptions {
+        CsvOptions {
+            input_columns: vec!("b".to_string(), "a".to_string()),
+            target_columns: vec!("label".to_string()),
+            normalize_inputs,
+            normalize_targets: Normalization::None,
+        }
+    }
+
+    #[test]
+    fn selects_columns_by_name() {
+        let data = Dataset::from_csv("a,b,label\n1,2,0\n3,4,1\n", &options(Normalization::None)).unwrap();
+
+        assert_eq!(data.inputs, vec!(vec!(2.0, 1.0), vec!(4.0, 3.0)));
+        assert_eq!(data.targets, vec!(vec!(0.0), vec!(1.0)));
+        assert_eq!(data.input_count(), 3);
+        assert_eq!(data.class_count(), 2);
+    }
+
+    #[test]
+    fn normalizes_and_rejects_bad_rows() {
+        let data = Dataset::from_csv("a,b,label\n1,2,0\n3,6,1\n", &options(Normalization::MinMax)).unwrap();
+        assert_eq!(data.inputs, vec!(vec!(0.0, 0.0), vec!(1.0, 1.0)));
+
+        assert!(Dataset::from_csv("a,b,label\n1,x,0\n", &options(Normalization::None)).is_err());
+        assert!(Dataset::from_csv("a,label\n1,0\n", &options(Normalization::None)).is_err());
+        assert!(Dataset::from_csv("a,b,label\n1,nan,0\n", &options(Normalization::None)).is_err());
+
+        let mut no_targets = options(Normalization::None);
+        no_targets.target_columns = Vec::new();
+        assert!(Dataset::from_csv("a,b,label\n1,2,0\n", &no_targets).is_err());
+    }
+
+    #[test]
+    fn class_labels_must_be_small_whole_numbers() {
+        let labels = |values: &[f32]| Dataset {
+            inputs: values.iter().map(|_| vec!(0.0)).collect(),
+            targets: values.iter().map(|&v| vec!(v)).collect(),
+        };
+
+        assert!(labels(&[0.0, 2.0, 1.0]).check_classes().is_ok());
+        assert_eq!(labels(&[0.0, 2.0, 1.0]).class_count(), 3);
+        assert!(labels(&[0.0, 1.5]).check_classes().is_err());
+        assert!(labels(&[-1.0, 1.0]).check_classes().is_err());
+        assert!(labels(&[0.0, 1e9]).check_classes().is_err());
+        assert!(Metric::Mse.check(&labels(&[0.5])).is_ok());
+        assert!(Metric::Accuracy.check(&labels(&[0.5])).is_err());
+    }
+
+    // Rows with a single input x, seen by the network alongside the bias input
+    fn rows(inputs: &[f32], targets: &[f32]) -> Dataset {
+        Dataset {
+            inputs: inputs.iter().map(|&x| vec!(x)).collect(),
+            targets: targets.iter().map(|&t| vec!(t)).collect(),
+        }
+    }
+
+    #[test]
+    fn mse_scores_the_mean_squared_error() {
+        // Without links the output is always 0
+        let n = Network::new(2, 1);
+        assert_eq!(evaluate_mse(&n, &rows(&[0.0, 0.0], &[0.0, 0.0])), 1.0);
+        assert_eq!(evaluate_mse(&n, &rows(&[0.0, 0.0], &[0.0, 1.0])), 1.0 / 1.5);
+    }
+
+    #[test]
+    fn accuracy_counts_rows_whose_largest_output_is_the_class() {
+        // Output 0 follows the input and output 1 stays at 0, so positive inputs are class 0
+        let mut n = Network::new(2, 2);
+        n.add_link(&mut 1, 0, 2, 5.0).unwrap();
+
+        assert_eq!(evaluate_accuracy(&n, &rows(&[1.0, -1.0, 2.0], &[0.0, 1.0, 1.0])), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn cross_entropy_rewards_confident_correct_outputs() {
+        // Equal outputs give each of the two classes probability 0.5
+        let n = Network::new(2, 2);
+        let data = rows(&[1.0, -1.0], &[0.0, 0.0]);
+        let uniform = evaluate_cross_entropy(&n, &data);
+        assert!((uniform - 1.0 / (1.0 + 2f32.ln())).abs() < 1e-6);
+
+        let mut confident = Network::new(2, 2);
+        confident.add_link(&mut 1, 1, 2, 5.0).unwrap();
+        assert!(evaluate_cross_entropy(&confident, &data) > uniform);
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::VecDeque;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::neat::*;
+use crate::network::*;
+
+// Fitness evaluation in worker processes, talking to the coordinator over TCP
+// on localhost. Every message is a line of text:
+//
+//   worker -> coordinator, once after connecting:  hello <pid>
+//   coordinator -> worker, for each genome:         evaluate <job>
+//                                                   <genome in Network::to_text format>
+//                                                   end
+//   worker -> coordinator, answering each genome:   fitness <job> <value>
+//                                                   error <job> <message>
+//
+// Fitness values must be finite; anything else is treated as a broken worker.
+// A worker that times out or drops the connection is killed and replaced, and
+// its genome is retried on another worker.
+
+// Time a newly spawned worker has to connect and say hello
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Time a worker waits for the coordinator to take its reply
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn invalid(line: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Invalid worker message: {}", line.trim()))
+}
+
+struct Worker {
+    child: Child,
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Worker {
+    // Outer error: the worker is lost. Inner error: the worker could not evaluate the genome.
+    fn evaluate(&mut self, job: usize, genome: &str, timeout: Duration) -> io::Result<Result<f32, String>> {
+        self.stream.set_read_timeout(Some(timeout))?;
+        self.stream.set_write_timeout(Some(timeout))?;
+        // One write per message, so small messages are not held back by Nagle's algorithm
+        self.stream.write_all(format!("evaluate {}\n{}end\n", job, genome).as_bytes())?;
+
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Worker closed the connection")),
+            Ok(_) => {},
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, format!("No answer within {:.1}s", timeout.as_secs_f64())));
+            },
+            Err(e) => return Err(e),
+        }
+
+        let fields: Vec<&str> = line.trim().splitn(3, ' ').collect();
+        if fields.len() < 2 || fields[1] != job.to_string() {
+            return Err(invalid(&line));
+        }
+        match fields[0] {
+            "fitness" => fields.get(2)
+                .and_then(|value| value.parse::<f32>().ok())
+                .filter(|fitness| fitness.is_finite())
+                .map(Ok)
+                .ok_or_else(|| invalid(&line)),
+            "error" => Ok(Err(fields.get(2).unwrap_or(&"unknown error").to_string())),
+            _ => Err(invalid(&line)),
+        }
+    }
+
+    fn kill(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// Local worker processes that evaluate genomes in parallel
+pub struct WorkerPool {
+    pub size: usize,
+    // Longest a worker may take to answer one genome before it is replaced
+    pub timeout: Duration,
+    // Extra attempts a genome gets after a failed evaluation
+    pub retries: u32,
+    // Fitness given to a genome that failed every attempt
+    pub failed_fitness: f32,
+
+    program: String,
+    args: Vec<String>,
+    listener: TcpListener,
+    workers: Vec<Worker>,
+}
+
+impl WorkerPool {
+    // Starts `size` copies of `program args... <coordinator address>`
+    pub fn spawn(program: &str, args: &[String], size: usize) -> io::Result<WorkerPool> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+
+        let mut pool = WorkerPool {
+            size,
+            timeout: Duration::from_secs(30),
+            retries: 2,
+            failed_fitness: 0.0,
+
+            program: program.to_string(),
+            args: args.to_vec(),
+            listener,
+            workers: Vec::new(),
+        };
+
+        pool.replace_lost_workers();
+        if pool.workers.len() == 0 {
+            return Err(io::Error::new(io::ErrorKind::Other, "No worker could be started"));
+        }
+
+        Ok(pool)
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    fn spawn_worker(&self) -> io::Result<Worker> {
+        let address = self.listener.local_addr()?;
+        // Workers only talk over the socket, so their output would just clutter ours
+        let mut child = Command::new(&self.program).args(&self.args).arg(address.to_string()).stdout(Stdio::null()).spawn()?;
+
+        match self.connect(&mut child) {
+            Ok((stream, reader)) => Ok(Worker { child, stream, reader }),
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(e)
+            },
+        }
+    }
+
+    // Waits for the child to connect and introduce itself
+    fn connect(&self, child: &mut Child) -> io::Result<(TcpStream, BufReader<TcpStream>)> {
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+
+        let stream = loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if let Some(status) = child.try_wait()? {
+                        return Err(io::Error::new(io::ErrorKind::Other, format!("Worker exited with {}", status)));
+                    }
+                    if Instant::now() > deadline {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, "Worker did not connect"));
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                },
+                Err(e) => return Err(e),
+            }
+        };
+
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if line.trim() != format!("hello {}", child.id()) {
+            return Err(invalid(&line));
+        }
+
+        Ok((stream, reader))
+    }
+
+    fn replace_lost_workers(&mut self) {
+        while self.workers.len() < self.size {
+            match self.spawn_worker() {
+                Ok(worker) => self.workers.push(worker),
+                Err(e) => {
+                    eprintln!("Failed to start worker {}: {}", self.program, e);
+                    break;
+                },
+            }
+        }
+    }
+
+    // Fitness of every network, in order
+    pub fn evaluate(&mut self, networks: &[Network]) -> Vec<f32> {
+        let genomes: Vec<String> = networks.iter().map(|n| n.to_text()).collect();
+        // Network index and failed attempts so far
+        let jobs: Mutex<VecDeque<(usize, u32)>> = Mutex::new((0..networks.len()).map(|i| (i, 0)).collect());
+        let results: Mutex<Vec<Option<f32>>> = Mutex::new(vec![None; networks.len()]);
+
+        let (retries, timeout) = (self.retries, self.timeout);
+        let retry = |index: usize, attempts: u32| {
+            if attempts < retries {
+                jobs.lock().unwrap().push_back((index, attempts + 1));
+            } else {
+                eprintln!("Giving up on genome {} after {} attempts", networks[index].id, attempts + 1);
+            }
+        };
+
+        while jobs.lock().unwrap().len() != 0 {
+            self.replace_lost_workers();
+            if self.workers.len() == 0 {
+                eprintln!("No workers left to evaluate {} genomes", jobs.lock().unwrap().len());
+                break;
+            }
+
+            // Each worker takes jobs until the queue is empty or the worker is lost
+            let alive: Vec<bool> = thread::scope(|scope| {
+                let handles: Vec<_> = self.workers.iter_mut().map(|worker| {
+                    let (jobs, results, genomes, retry) = (&jobs, &results, &genomes, &retry);
+                    scope.spawn(move || loop {
+                        let (index, attempts) = match jobs.lock().unwrap().pop_front() {
+                            Some(job) => job,
+                            None => return true,
+                        };
+
+                        match worker.evaluate(index, &genomes[index], timeout) {
+                            Ok(Ok(fitness)) => results.lock().unwrap()[index] = Some(fitness),
+                            Ok(Err(message)) => {
+                                eprintln!("Worker failed to evaluate genome {}: {}", networks[index].id, message);
+                                retry(index, attempts);
+                            },
+                            Err(e) => {
+                                eprintln!("Lost worker {}: {}", worker.child.id(), e);
+                                retry(index, attempts);
+                                return false;
+                            },
+                        }
+                    })
+                }).collect();
+
+                handles.into_iter().map(|handle| handle.join().unwrap_or(false)).collect()
+            });
+
+            let mut i = 0;
+            self.workers.retain_mut(|worker| {
+                let keep = alive[i];
+                i += 1;
+                if !keep {
+                    worker.kill();
+                }
+                keep
+            });
+        }
+
+        results.into_inner().unwrap().into_iter().map(|fitness| fitness.unwrap_or(self.failed_fitness)).collect()
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        for worker in &mut self.workers {
+            worker.kill();
+        }
+    }
+}
+
+impl NEAT {
+    // Runs one generation with every network scored by the worker pool
+    pub fn train_distributed(&mut self, pool: &mut WorkerPool) {
+        let fitnesses = pool.evaluate(&self.pop);
+
+        // train_evaluated visits the population in order
+        let mut index = 0;
+        self.train_evaluated(|n| {
+            n.fitness = fitnesses[index];
+            index += 1;
+        });
+    }
+}
+
+// Worker side: connects to the coordinator and answers genomes until it hangs up
+pub fn run_worker<F: FnMut(&Network) -> f32>(address: &str, mut fitness: F) -> io::Result<()> {
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(REPLY_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    stream.write_all(format!("hello {}\n", std::process::id()).as_bytes())?;
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let job = match line.trim().strip_prefix("evaluate ") {
+            Some(job) => job.to_string(),
+            None => return Err(invalid(&line)),
+        };
+
+        let mut genome = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Coordinator closed the connection mid-genome"));
+            }
+            if line.trim() == "end" {
+                break;
+            }
+            genome += line.as_str();
+        }
+
+        let reply = match Network::from_text(&genome).map(|n| fitness(&n)) {
+            Ok(fitness) if fitness.is_finite() => format!("fitness {} {}\n", job, fitness),
+            Ok(fitness) => format!("error {} fitness is {}\n", job, fitness),
+            Err(e) => format!("error {} {}\n", job, e),
+        };
+        stream.write_all(reply.as_bytes())?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::fs::OpenOptions;
+    use std::path::PathBuf;
+    use std::process;
+
+    // Workers are copies of this test binary running worker_process, which
+    // reads how to behave from its arguments
+    fn pool(behavior: &str, size: usize) -> WorkerPool {
+        let program = env::current_exe().unwrap().to_string_lossy().to_string();
+        let args: Vec<String> = ["distributed::tests::worker_process", "--exact", "--ignored", "--quiet", behavior]
+            .iter().map(|arg| arg.to_string()).collect();
+        WorkerPool::spawn(&program, &args, size).unwrap()
+    }
+
+    // Networks with 0 to 5 links, which the workers score as links + 0.5
+    fn networks() -> Vec<Network> {
+        (0..6).map(|links| {
+            let mut n = Network::new(3, 2);
+            let mut inno = 0;
+            for i in 0..links {
+                n.add_link(&mut inno, i % 3, 3 + i / 3, 1.0).unwrap();
+            }
+            n
+        }).collect()
+    }
+
+    fn marker(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("neat-{}-{}", name, process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    // Not a test on its own: the worker side of the tests below. Behaviours are
+    // `score`, `hang`, `die-once:<marker file>` and `die:<log file>`.
+    #[test]
+    #[ignore]
+    fn worker_process() {
+        let args: Vec<String> = env::args().collect();
+        let (behavior, address) = match args.as_slice() {
+            [.., behavior, address] if address.parse::<std::net::SocketAddr>().is_ok() => (behavior.clone(), address.clone()),
+            _ => return,
+        };
+
+        let _ = run_worker(&address, |n| {
+            match behavior.split_once(':') {
+                Some(("die-once", path)) => {
+                    if OpenOptions::new().write(true).create_new(true).open(path).is_ok() {
+                        process::exit(1);
+                    }
+                },
+                Some(("die", path)) => {
+                    let mut log = OpenOptions::new().append(true).create(true).open(path).unwrap();
+                    writeln!(log, "died").unwrap();
+                    process::exit(1);
+                },
+                _ if behavior == "hang" => thread::sleep(Duration::from_secs(10)),
+                _ => {},
+            }
+            n.links.len() as f32 + 0.5
+        });
+    }
+
+    #[test]
+    fn workers_score_every_genome() {
+        let mut pool = pool("score", 2);
+        assert_eq!(pool.worker_count(), 2);
+        assert_eq!(pool.evaluate(&networks()), vec!(0.5, 1.5, 2.5, 3.5, 4.5, 5.5));
+    }
+
+    #[test]
+    fn silent_workers_time_out() {
+        let mut pool = pool("hang", 1);
+        pool.timeout = Duration::from_millis(200);
+        pool.retries = 0;
+        pool.failed_fitness = -1.0;
+
+        let start = Instant::now();
+        assert_eq!(pool.evaluate(&networks()[..1]), vec!(-1.0));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn genomes_of_lost_workers_are_retried() {
+        let path = marker("die-once");
+        let mut pool = pool(&format!("die-once:{}", path.display()), 2);
+
+        assert_eq!(pool.evaluate(&networks()), vec!(0.5, 1.5, 2.5, 3.5, 4.5, 5.5));
+        assert!(path.exists());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn genomes_are_given_up_after_retries() {
+        let path = marker("die");
+        let mut pool = pool(&format!("die:{}", path.display()), 1);
+        pool.retries = 2;
+        pool.failed_fitness = -1.0;
+
+        assert_eq!(pool.evaluate(&networks()[..1]), vec!(-1.0));
+        // One first attempt and two retries, each killing its worker
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        let _ = fs::remove_file(&path);
+    }
+}
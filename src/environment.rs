@@ -0,0 +1,134 @@
+use crate::network::*;
+
+// An episodic task a network interacts with one step at a time
+pub trait Environment {
+    // Starts a new episode
+    fn reset(&mut self);
+
+    // Network inputs for the current state, including any bias input
+    fn observe(&self) -> Vec<f32>;
+
+    // Applies the network outputs and returns the reward for this step
+    fn step(&mut self, actions: &[f32]) -> f32;
+
+    fn done(&self) -> bool;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EpisodeConfig {
+    pub episodes: u32,
+    // Episodes are cut off after this many steps even if not done
+    pub max_steps: u32,
+}
+
+// Runs one episode from a fresh reset and returns the total reward
+pub fn run_episode<E: Environment>(n: &Network, env: &mut E, max_steps: u32) -> f32 {
+    env.reset();
+
+    let mut reward = 0.0;
+    let mut steps = 0;
+    while !env.done() && steps < max_steps {
+        let actions = n.evaluate(env.observe());
+        reward += env.step(&actions);
+        steps += 1;
+    }
+
+    reward
+}
+
+// Mean total reward over several episodes, usable as a fitness function
+pub fn evaluate_episodes<E: Environment>(n: &Network, env: &mut E, config: EpisodeConfig) -> f32 {
+    if config.episodes == 0 {
+        return 0.0;
+    }
+
+    let mut total = 0.0;
+    for _ in 0..config.episodes {
+        total += run_episode(n, env, config.max_steps);
+    }
+
+    total / config.episodes as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ends after `length` steps and rewards each step with the number of the episode
+    struct Countdown {
+        length: u32,
+        steps: u32,
+        episode: u32,
+        observed: Vec<f32>,
+    }
+
+    impl Environment for Countdown {
+        fn reset(&mut self) {
+            self.steps = 0;
+            self.episode += 1;
+        }
+
+        fn observe(&self) -> Vec<f32> {
+            vec!(1.0)
+        }
+
+        fn step(&mut self, actions: &[f32]) -> f32 {
+            self.observed.push(actions[0]);
+            self.steps += 1;
+            self.episode as f32
+        }
+
+        fn done(&self) -> bool {
+            self.steps >= self.length
+        }
+    }
+
+    fn countdown(length: u32) -> Countdown {
+        Countdown { length, steps: 0, episode: 0, observed: Vec::new() }
+    }
+
+    #[test]
+    fn episodes_end_when_done_or_at_the_step_limit() {
+        let network = Network::new(1, 1);
+
+        let mut env = countdown(3);
+        assert_eq!(run_episode(&network, &mut env, 10), 3.0);
+        assert_eq!(env.observed.len(), 3);
+
+        let mut env = countdown(100);
+        assert_eq!(run_episode(&network, &mut env, 10), 10.0);
+        assert_eq!(env.steps, 10);
+    }
+
+    #[test]
+    fn rewards_are_summed_over_steps_with_the_network_outputs() {
+        let mut network = Network::new(1, 1);
+        let mut inno = 1;
+        network.add_link(&mut inno, 0, 1, 1.0).unwrap();
+
+        let mut env = countdown(4);
+        assert_eq!(run_episode(&network, &mut env, 10), 4.0);
+        assert_eq!(env.observed, vec!(Network::sigmoid(1.0); 4));
+    }
+
+    #[test]
+    fn fitness_is_the_mean_over_episodes() {
+        let network = Network::new(1, 1);
+        let mut env = countdown(2);
+
+        // Episodes score 2, 4 and 6
+        let config = EpisodeConfig { episodes: 3, max_steps: 10 };
+        assert_eq!(evaluate_episodes(&network, &mut env, config), 4.0);
+        assert_eq!(env.episode, 3);
+    }
+
+    #[test]
+    fn no_episodes_score_zero() {
+        let network = Network::new(1, 1);
+        let mut env = countdown(2);
+
+        let config = EpisodeConfig { episodes: 0, max_steps: 10 };
+        assert_eq!(evaluate_episodes(&network, &mut env, config), 0.0);
+        assert_eq!(env.episode, 0);
+    }
+}
@@ -0,0 +1,47 @@
+use std::error;
+use std::fmt;
+use std::fmt::{Formatter, Display};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NeatError {
+    // A node index did not refer to a node in the network
+    InvalidNode(usize),
+
+    // A link index did not refer to a link in the network
+    InvalidLink(usize),
+
+    // Both nodes had the same type and level, so no direction could be chosen
+    SameLevel(usize, usize),
+
+    // A link between the two nodes already exists
+    LinkExists(usize, usize),
+
+    // Every valid pair of nodes is already linked
+    FullyConnected,
+
+    // The network had no enabled links to split
+    NoLinksAvailable,
+
+    // A run was started without any termination condition
+    Unbounded,
+
+    // A HyperNEAT weight threshold outside [0, 1)
+    InvalidWeightThreshold(f32),
+}
+
+impl Display for NeatError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            NeatError::InvalidNode(index) => write!(f, "Node {} does not exist", index),
+            NeatError::InvalidLink(index) => write!(f, "Link {} does not exist", index),
+            NeatError::SameLevel(a, b) => write!(f, "Nodes {} and {} have the same type and level", a, b),
+            NeatError::LinkExists(a, b) => write!(f, "A link from {} to {} already exists", a, b),
+            NeatError::FullyConnected => write!(f, "Network is fully connected"),
+            NeatError::NoLinksAvailable => write!(f, "No links available"),
+            NeatError::Unbounded => write!(f, "Run has no termination condition"),
+            NeatError::InvalidWeightThreshold(threshold) => write!(f, "Weight threshold {} is not in [0, 1)", threshold),
+        }
+    }
+}
+
+impl error::Error for NeatError {}
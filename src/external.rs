@@ -0,0 +1,256 @@
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::network::*;
+
+// Fitness from an external program, so simulators written in other languages
+// can score genomes. The program is started once and kept running, reading
+// requests on stdin and answering on stdout, one line per message. Its stderr
+// is passed through, so logging should go there.
+//
+// For each genome the program receives
+//
+//   genome
+//   <genome in Network::to_text format>
+//   end
+//
+// and then sends any number of
+//
+//   evaluate <input 1> ... <input n>
+//
+// each answered with `outputs <output 1> ... <output m>` from Network::evaluate
+// (or `error <message>` if the inputs are malformed), and finishes the genome
+// with one of
+//
+//   fitness <value>
+//   error <message>
+//
+// where the fitness must be a finite number.
+// A program that knows the genome format can evaluate the network itself and
+// reply with the fitness straight away. n counts the bias input. Stdout must be
+// flushed after every line. A program that exits, times out or sends anything
+// else is killed and restarted for the next genome.
+
+// Writes the program's stdin and reads its stdout on separate threads, so a
+// program that stops reading or answering can time out
+struct ExternalProcess {
+    child: Child,
+    messages: Sender<String>,
+    written: Receiver<io::Result<()>>,
+    lines: Receiver<String>,
+}
+
+impl ExternalProcess {
+    fn spawn(program: &str, args: &[String]) -> io::Result<ExternalProcess> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let mut stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (messages, pending) = mpsc::channel::<String>();
+        let (done, written) = mpsc::channel();
+        thread::spawn(move || {
+            for message in pending {
+                let result = stdin.write_all(message.as_bytes()).and_then(|_| stdin.flush());
+                let failed = result.is_err();
+                if done.send(result).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if sender.send(line).is_err() {
+                        break;
+                    },
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(ExternalProcess { child, messages, written, lines })
+    }
+
+    fn send(&self, message: &str, timeout: Duration) -> io::Result<()> {
+        let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "Program closed its input");
+        self.messages.send(message.to_string()).map_err(|_| closed())?;
+
+        match self.written.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(io::ErrorKind::TimedOut, format!("Input not read within {:.1}s", timeout.as_secs_f64()))),
+            Err(RecvTimeoutError::Disconnected) => Err(closed()),
+        }
+    }
+
+    fn receive(&self, timeout: Duration) -> io::Result<String> {
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(io::ErrorKind::TimedOut, format!("No answer within {:.1}s", timeout.as_secs_f64()))),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Program closed its output")),
+        }
+    }
+
+    fn kill(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct ExternalFitness {
+    // Longest the program may take to read or send a line
+    pub timeout: Duration,
+    // Fitness given to a genome the program failed to score
+    pub failed_fitness: f32,
+
+    program: String,
+    args: Vec<String>,
+    process: Option<ExternalProcess>,
+}
+
+impl ExternalFitness {
+    // The program is started on the first evaluation
+    pub fn new(program: &str, args: &[String]) -> Self {
+        ExternalFitness {
+            timeout: Duration::from_secs(30),
+            failed_fitness: 0.0,
+
+            program: program.to_string(),
+            args: args.to_vec(),
+            process: None,
+        }
+    }
+
+    // Runs a shell command line, e.g. "python3 sim.py --level 2"
+    pub fn shell(command: &str) -> Self {
+        ExternalFitness::new("sh", &["-c".to_string(), command.to_string()])
+    }
+
+    pub fn fitness(&mut self, n: &Network) -> f32 {
+        match self.evaluate(n) {
+            Ok(Ok(fitness)) => fitness,
+            Ok(Err(message)) => {
+                eprintln!("{} could not score genome {}: {}", self.program, n.id, message);
+                self.failed_fitness
+            },
+            Err(e) => {
+                eprintln!("{} failed on genome {}: {}", self.program, n.id, e);
+                if let Some(mut process) = self.process.take() {
+                    process.kill();
+                }
+                self.failed_fitness
+            },
+        }
+    }
+
+    // Outer error: the program has to be restarted. Inner error: it reported a failure.
+    fn evaluate(&mut self, n: &Network) -> io::Result<Result<f32, String>> {
+        if self.process.is_none() {
+            self.process = Some(ExternalProcess::spawn(&self.program, &self.args)?);
+        }
+        let process = self.process.as_ref().unwrap();
+
+        process.send(format!("genome\n{}end\n", n.to_text()).as_str(), self.timeout)?;
+
+        loop {
+            let line = process.receive(self.timeout)?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields.first().cloned() {
+                Some("evaluate") => {
+                    let inputs: Result<Vec<f32>, _> = fields[1..].iter().map(|f| f.parse()).collect();
+                    let reply = match inputs {
+                        Ok(inputs) if inputs.len() == n.input_count as usize => {
+                            let outputs: Vec<String> = n.evaluate(inputs).iter().map(|o| o.to_string()).collect();
+                            format!("outputs {}\n", outputs.join(" "))
+                        },
+                        _ => format!("error expected {} numeric inputs\n", n.input_count),
+                    };
+                    process.send(reply.as_str(), self.timeout)?;
+                },
+                Some("fitness") if fields.len() == 2 => {
+                    return fields[1].parse::<f32>().ok()
+                        .filter(|fitness| fitness.is_finite())
+                        .map(Ok)
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid fitness: {}", line)));
+                },
+                Some("error") => return Ok(Err(fields[1..].join(" "))),
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unexpected line: {}", line))),
+            }
+        }
+    }
+}
+
+impl Drop for ExternalFitness {
+    fn drop(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            process.kill();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network() -> Network {
+        let mut n = Network::new(3, 1);
+        n.add_link(&mut 0, 0, 3, 1.5).unwrap();
+        n.add_link(&mut 1, 2, 3, -0.5).unwrap();
+        n
+    }
+
+    #[test]
+    fn program_can_score_genomes_directly() {
+        let mut external = ExternalFitness::shell("while read line; do [ \"$line\" = end ] && echo 'fitness 0.25'; done");
+        assert_eq!(external.fitness(&network()), 0.25);
+        assert_eq!(external.fitness(&network()), 0.25);
+    }
+
+    #[test]
+    fn program_can_drive_the_network() {
+        let script = "while read line; do [ \"$line\" = end ] && echo 'evaluate 1 0 1' && read reply && echo \"fitness ${reply#outputs }\"; done";
+        let mut external = ExternalFitness::shell(script);
+
+        let n = network();
+        assert_eq!(external.fitness(&n), n.evaluate(vec!(1.0, 0.0, 1.0))[0]);
+    }
+
+    #[test]
+    fn silent_program_times_out() {
+        let mut external = ExternalFitness::shell("sleep 5");
+        external.timeout = Duration::from_millis(100);
+        external.failed_fitness = -1.0;
+        assert_eq!(external.fitness(&network()), -1.0);
+    }
+
+    #[test]
+    fn program_not_reading_its_input_times_out() {
+        // The genome is far larger than a pipe buffer, so writing it blocks
+        let mut external = ExternalFitness::shell("sleep 10");
+        external.timeout = Duration::from_millis(200);
+        external.failed_fitness = -1.0;
+
+        let start = std::time::Instant::now();
+        assert_eq!(external.fitness(&Network::new(20000, 1)), -1.0);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn non_finite_fitness_is_rejected() {
+        let mut external = ExternalFitness::shell("while read line; do [ \"$line\" = end ] && echo 'fitness nan'; done");
+        external.failed_fitness = -1.0;
+        assert_eq!(external.fitness(&network()), -1.0);
+    }
+}
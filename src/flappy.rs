@@ -0,0 +1,252 @@
+use sdl2::render::WindowCanvas;
+use sdl2::gfx::primitives::DrawRenderer;
+
+use crate::environment::*;
+use crate::network::*;
+
+// World units: the screen is 1 wide and 1 tall, with y pointing down
+const BIRD_X: f32 = 0.2;
+const BIRD_RADIUS: f32 = 0.02;
+const GRAVITY: f32 = 0.0015;
+const FLAP_VELOCITY: f32 = -0.02;
+const PIPE_SPEED: f32 = 0.006;
+const PIPE_SPACING: f32 = 0.5;
+const PIPE_WIDTH: f32 = 0.08;
+const GAP_HEIGHT: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pipe {
+    pub x: f32,
+    pub gap_center: f32,
+}
+
+// Side-scrolling obstacle game. Pipe gaps come from a seeded generator, so a
+// network always sees the same course and scores deterministically.
+#[derive(Debug, Clone)]
+pub struct Flappy {
+    pub seed: u64,
+    pub max_steps: u32,
+
+    pub bird_y: f32,
+    pub bird_velocity: f32,
+    pub pipes: Vec<Pipe>,
+    pub steps: u32,
+    pub score: u32,
+    pub crashed: bool,
+    rng_state: u64,
+}
+
+impl Flappy {
+    // Network inputs including the bias input
+    pub const INPUT_COUNT: u32 = 5;
+
+    pub fn new(seed: u64) -> Self {
+        let mut flappy = Flappy {
+            seed,
+            max_steps: 5000,
+
+            bird_y: 0.5,
+            bird_velocity: 0.0,
+            pipes: Vec::new(),
+            steps: 0,
+            score: 0,
+            crashed: false,
+            rng_state: seed,
+        };
+        flappy.reset();
+        flappy
+    }
+
+    // Linear congruential generator in [0, 1)
+    fn next_random(&mut self) -> f32 {
+        self.rng_state = self.rng_state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.rng_state >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn spawn_pipes(&mut self) {
+        while self.pipes.last().map_or(true, |pipe| pipe.x < 1.0) {
+            let x = self.pipes.last().map_or(0.8, |pipe| pipe.x + PIPE_SPACING);
+            let gap_center = 0.25 + self.next_random() * 0.5;
+            self.pipes.push(Pipe { x, gap_center });
+        }
+    }
+
+    // Behaviour descriptor for novelty search: the bird's height and how far it flew
+    pub fn behavior(&self) -> Vec<f32> {
+        vec!(self.bird_y, self.steps as f32 / self.max_steps as f32)
+    }
+
+    // First pipe the bird has not yet flown past
+    pub fn next_pipe(&self) -> Option<&Pipe> {
+        self.pipes.iter().find(|pipe| pipe.x + PIPE_WIDTH >= BIRD_X - BIRD_RADIUS)
+    }
+
+    fn collides(&self) -> bool {
+        if self.bird_y - BIRD_RADIUS < 0.0 || self.bird_y + BIRD_RADIUS > 1.0 {
+            return true;
+        }
+
+        self.pipes.iter().any(|pipe| {
+            let overlaps_x = BIRD_X + BIRD_RADIUS > pipe.x && BIRD_X - BIRD_RADIUS < pipe.x + PIPE_WIDTH;
+            let in_gap = (self.bird_y - pipe.gap_center).abs() + BIRD_RADIUS < GAP_HEIGHT / 2.0;
+            overlaps_x && !in_gap
+        })
+    }
+
+    pub fn draw(&self, canvas: &mut WindowCanvas, x: f32, y: f32, width: f32, height: f32) {
+        let to_screen = |wx: f32, wy: f32| -> (i16, i16) { ((x + wx * width) as i16, (y + wy * height) as i16) };
+
+        // Border
+        canvas.rectangle(x as i16, y as i16, (x + width) as i16, (y + height) as i16, (120, 120, 120, 255))
+            .expect("Failed to draw border");
+
+        // Pipes, clipped to the play area
+        for pipe in &self.pipes {
+            let left = pipe.x.max(0.0);
+            let right = (pipe.x + PIPE_WIDTH).min(1.0);
+            if right <= left {
+                continue;
+            }
+
+            let (x1, top) = to_screen(left, 0.0);
+            let (x2, gap_top) = to_screen(right, pipe.gap_center - GAP_HEIGHT / 2.0);
+            let (_, gap_bottom) = to_screen(right, pipe.gap_center + GAP_HEIGHT / 2.0);
+            let (_, bottom) = to_screen(right, 1.0);
+
+            canvas.box_(x1, top, x2, gap_top, (0, 180, 0, 255))
+                .expect("Failed to draw pipe");
+            canvas.box_(x1, gap_bottom, x2, bottom, (0, 180, 0, 255))
+                .expect("Failed to draw pipe");
+        }
+
+        // Bird
+        let (bird_x, bird_y) = to_screen(BIRD_X, self.bird_y);
+        let color: (u8, u8, u8, u8) = if self.crashed { (255, 0, 0, 255) } else { (255, 220, 0, 255) };
+        canvas.filled_circle(bird_x, bird_y, (BIRD_RADIUS * height) as i16, color)
+            .expect("Failed to draw bird");
+    }
+}
+
+// Flaps whenever the first output is positive and rewards every step survived
+impl Environment for Flappy {
+    fn reset(&mut self) {
+        self.bird_y = 0.5;
+        self.bird_velocity = 0.0;
+        self.pipes = Vec::new();
+        self.steps = 0;
+        self.score = 0;
+        self.crashed = false;
+        self.rng_state = self.seed;
+        self.spawn_pipes();
+    }
+
+    fn observe(&self) -> Vec<f32> {
+        let (pipe_distance, gap_offset) = match self.next_pipe() {
+            Some(pipe) => ((pipe.x - BIRD_X) / PIPE_SPACING, (pipe.gap_center - self.bird_y) * 2.0),
+            None => (1.0, 0.0),
+        };
+
+        vec!(self.bird_y * 2.0 - 1.0, self.bird_velocity / FLAP_VELOCITY.abs(), pipe_distance, gap_offset, 1.0)
+    }
+
+    fn step(&mut self, actions: &[f32]) -> f32 {
+        if actions[0] > 0.0 {
+            self.bird_velocity = FLAP_VELOCITY;
+        }
+        self.bird_velocity += GRAVITY;
+        self.bird_y += self.bird_velocity;
+
+        for pipe in &mut self.pipes {
+            let was_ahead = pipe.x + PIPE_WIDTH >= BIRD_X;
+            pipe.x -= PIPE_SPEED;
+            if was_ahead && pipe.x + PIPE_WIDTH < BIRD_X {
+                self.score += 1;
+            }
+        }
+        self.pipes.retain(|pipe| pipe.x + PIPE_WIDTH > 0.0);
+        self.spawn_pipes();
+
+        self.steps += 1;
+        self.crashed = self.collides();
+        1.0
+    }
+
+    fn done(&self) -> bool {
+        self.crashed || self.steps >= self.max_steps
+    }
+}
+
+// Fraction of the step limit survived on a fixed course
+pub fn evaluate_flappy(n: &Network) -> f32 {
+    let mut flappy = Flappy::new(1);
+    let config = EpisodeConfig { episodes: 1, max_steps: flappy.max_steps };
+    evaluate_episodes(n, &mut flappy, config) / flappy.max_steps as f32
+}
+
+// Same fitness as evaluate_flappy, along with the behaviour at the end of the episode
+pub fn evaluate_flappy_behavior(n: &Network) -> (f32, Vec<f32>) {
+    let mut flappy = Flappy::new(1);
+    let max_steps = flappy.max_steps;
+    let steps = run_episode(n, &mut flappy, max_steps);
+    (steps / max_steps as f32, flappy.behavior())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Flaps whenever the next gap is above the bird
+    fn gap_follower() -> Network {
+        let mut network = Network::new(Flappy::INPUT_COUNT, 1);
+        let mut inno = 1;
+        network.add_link(&mut inno, 3, Flappy::INPUT_COUNT as usize, -5.0).unwrap();
+        network
+    }
+
+    #[test]
+    fn same_network_and_seed_score_the_same() {
+        let network = gap_follower();
+        assert_eq!(evaluate_flappy(&network), evaluate_flappy(&network));
+
+        let mut first = Flappy::new(7);
+        let mut second = Flappy::new(7);
+        let first_reward = run_episode(&network, &mut first, 5000);
+        let second_reward = run_episode(&network, &mut second, 5000);
+        assert_eq!(first_reward, second_reward);
+        assert_eq!((first.steps, first.score, first.bird_y), (second.steps, second.score, second.bird_y));
+    }
+
+    #[test]
+    fn falling_bird_hits_the_ground() {
+        let mut flappy = Flappy::new(1);
+        while !flappy.done() {
+            flappy.step(&[0.0]);
+        }
+
+        assert!(flappy.crashed);
+        assert!(flappy.steps < flappy.max_steps);
+        assert!(flappy.bird_y + BIRD_RADIUS > 1.0);
+    }
+
+    #[test]
+    fn flying_into_a_pipe_ends_the_episode() {
+        let mut flappy = Flappy::new(1);
+        flappy.bird_y = 0.2;
+        flappy.pipes = vec!(Pipe { x: BIRD_X, gap_center: 0.8 });
+
+        flappy.step(&[1.0]);
+        assert!(flappy.crashed);
+        assert!(flappy.done());
+    }
+
+    #[test]
+    fn passing_a_pipe_scores_a_point() {
+        let mut flappy = Flappy::new(1);
+        flappy.pipes = vec!(Pipe { x: BIRD_X - PIPE_WIDTH + PIPE_SPEED / 2.0, gap_center: flappy.bird_y });
+        assert_eq!(flappy.score, 0);
+
+        flappy.step(&[0.0]);
+        assert_eq!(flappy.score, 1);
+        assert!(!flappy.crashed);
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Formatter, Display};
+
+use crate::network::*;
+
+// A structural or weight change made to a genome when it was born
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mutation {
+    PerturbWeights,
+    RandomizeWeights,
+    // Hidden node and the innovation number of the first of its two new links
+    AddNode { node: usize, inno: u32 },
+    AddLink { inno: u32 },
+    ReenableLink { inno: u32 },
+}
+
+impl Display for Mutation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Mutation::PerturbWeights => write!(f, "perturb weights"),
+            Mutation::RandomizeWeights => write!(f, "randomize weights"),
+            Mutation::AddNode { node, inno } => write!(f, "add node {} (links {} and {})", node, inno, inno + 1),
+            Mutation::AddLink { inno } => write!(f, "add link {}", inno),
+            Mutation::ReenableLink { inno } => write!(f, "re-enable link {}", inno),
+        }
+    }
+}
+
+// Where a genome came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lineage {
+    // Empty for the initial population
+    pub parents: Vec<u64>,
+    // Generation the genome was first evaluated in
+    pub generation: u32,
+    // Species of the parent when the genome was born
+    pub species: Option<u32>,
+    // Empty for an unchanged copy of its parent
+    pub mutations: Vec<Mutation>,
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        Lineage {
+            parents: Vec::new(),
+            generation: 0,
+            species: None,
+            mutations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ancestor {
+    pub id: u64,
+    pub lineage: Lineage,
+    // Fitness from the genome's evaluation, 0 until it has been evaluated
+    pub fitness: f32,
+}
+
+// Lineage of every genome created during a run, by id
+#[derive(Debug, Clone)]
+pub struct Genealogy {
+    pub records: HashMap<u64, Ancestor>,
+}
+
+impl Genealogy {
+    pub fn new() -> Self {
+        Genealogy {
+            records: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, n: &Network) {
+        self.records.insert(n.id, Ancestor { id: n.id, lineage: n.lineage.clone(), fitness: n.fitness });
+    }
+
+    pub fn record_fitness(&mut self, id: u64, fitness: f32) {
+        if let Some(ancestor) = self.records.get_mut(&id) {
+            ancestor.fitness = fitness;
+        }
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Ancestor> {
+        self.records.get(&id)
+    }
+
+    // The genome and all of its recorded ancestors, youngest first
+    pub fn ancestors(&self, id: u64) -> Vec<&Ancestor> {
+        let mut found: Vec<&Ancestor> = Vec::new();
+        let mut queue: Vec<u64> = vec!(id);
+
+        while queue.len() != 0 {
+            let current = queue.remove(0);
+            if found.iter().any(|a| a.id == current) {
+                continue;
+            }
+            if let Some(ancestor) = self.get(current) {
+                found.push(ancestor);
+                queue.extend(ancestor.lineage.parents.iter().cloned());
+            }
+        }
+
+        found
+    }
+
+    // Ancestry tree as a Graphviz digraph with edges from parent to child
+    pub fn to_dot(&self, id: u64) -> String {
+        let ancestors = self.ancestors(id);
+
+        let mut output = String::from("digraph ancestry {\n    rankdir=BT;\n");
+        for ancestor in &ancestors {
+            let species = ancestor.lineage.species.map_or("-".to_string(), |s| s.to_string());
+            let mut label = format!("{}\\ngeneration {}, species {}\\nfitness {:.4}", ancestor.id, ancestor.lineage.generation, species, ancestor.fitness);
+            for mutation in &ancestor.lineage.mutations {
+                label += format!("\\n{}", mutation).as_str();
+            }
+            output += format!("    {} [shape=box, label=\"{}\"];\n", ancestor.id, label).as_str();
+        }
+        for ancestor in &ancestors {
+            for parent in &ancestor.lineage.parents {
+                output += format!("    {} -> {};\n", parent, ancestor.id).as_str();
+            }
+        }
+        output += "}\n";
+
+        output
+    }
+
+    // Ancestry tree as JSON: the genome's id and a list of it and its ancestors
+    pub fn to_json(&self, id: u64) -> String {
+        let genomes: Vec<String> = self.ancestors(id).iter().map(|ancestor| {
+            let parents: Vec<String> = ancestor.lineage.parents.iter().map(|p| p.to_string()).collect();
+            let mutations: Vec<String> = ancestor.lineage.mutations.iter().map(|m| format!("\"{}\"", m)).collect();
+            let species = ancestor.lineage.species.map_or("null".to_string(), |s| s.to_string());
+            let fitness = if ancestor.fitness.is_finite() { ancestor.fitness.to_string() } else { "null".to_string() };
+
+            format!("{{\"id\": {}, \"parents\": [{}], \"generation\": {}, \"species\": {}, \"fitness\": {}, \"mutations\": [{}]}}",
+                ancestor.id, parents.join(", "), ancestor.lineage.generation, species, fitness, mutations.join(", "))
+        }).collect();
+
+        format!("{{\"id\": {}, \"genomes\": [\n    {}\n]}}\n", id, genomes.join(",\n    "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(id: u64, parents: Vec<u64>, mutations: Vec<Mutation>) -> Network {
+        let mut n = Network::new(2, 1);
+        n.id = id;
+        n.lineage = Lineage { parents, generation: id as u32, species: Some(1), mutations };
+        n
+    }
+
+    #[test]
+    fn ancestry_follows_parents() {
+        let mut genealogy = Genealogy::new();
+        genealogy.record(&network(1, vec!(), vec!()));
+        genealogy.record(&network(2, vec!(), vec!()));
+        genealogy.record(&network(3, vec!(1), vec!(Mutation::AddLink { inno: 4 })));
+        genealogy.record(&network(4, vec!(3), vec!(Mutation::PerturbWeights)));
+
+        let ids: Vec<u64> = genealogy.ancestors(4).iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec!(4, 3, 1));
+
+        let dot = genealogy.to_dot(4);
+        assert!(dot.contains("1 -> 3;"));
+        assert!(dot.contains("3 -> 4;"));
+        assert!(!dot.contains("2 ["));
+        assert!(genealogy.to_json(4).contains("\"mutations\": [\"add link 4\"]"));
+    }
+}
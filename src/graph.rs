@@ -0,0 +1,62 @@
+use sdl2::render::WindowCanvas;
+use sdl2::gfx::primitives::DrawRenderer;
+
+use crate::stats::*;
+
+// Plots best and mean fitness over generations, scaling both axes to fit the data
+pub fn draw_fitness_graph(canvas: &mut WindowCanvas, stats: &[GenerationStats], x: f32, y: f32, width: f32, height: f32) {
+    let axis_color: (u8, u8, u8, u8) = (120, 120, 120, 255);
+    let best_color: (u8, u8, u8, u8) = (0, 255, 0, 255);
+    let mean_color: (u8, u8, u8, u8) = (255, 200, 0, 255);
+
+    // Axes
+    canvas.aa_line(x as i16, y as i16, x as i16, (y + height) as i16, axis_color)
+        .expect("Failed to draw axis");
+    canvas.aa_line(x as i16, (y + height) as i16, (x + width) as i16, (y + height) as i16, axis_color)
+        .expect("Failed to draw axis");
+
+    if stats.len() < 2 {
+        return;
+    }
+
+    // Find fitness range
+    let mut min_fitness = std::f32::MAX;
+    let mut max_fitness = std::f32::MIN;
+    for s in stats {
+        min_fitness = min_fitness.min(s.mean_fitness).min(s.best_fitness);
+        max_fitness = max_fitness.max(s.mean_fitness).max(s.best_fitness);
+    }
+
+    // Keep a flat curve from dividing by zero
+    if max_fitness - min_fitness < std::f32::EPSILON {
+        max_fitness += 0.5;
+        min_fitness -= 0.5;
+    }
+
+    let x_spacing = width / (stats.len() - 1) as f32;
+    let to_screen = |i: usize, fitness: f32| -> (i16, i16) {
+        let px = x + x_spacing * i as f32;
+        let py = y + height - (fitness - min_fitness) / (max_fitness - min_fitness) * height;
+        (px as i16, py as i16)
+    };
+
+    // Horizontal guide lines at quarters of the range
+    for i in 1..4 {
+        let guide_y = (y + height * i as f32 / 4.0) as i16;
+        canvas.hline(x as i16, (x + width) as i16, guide_y, (40, 40, 40, 255))
+            .expect("Failed to draw guide line");
+    }
+
+    // Curves
+    for i in 1..stats.len() {
+        let (x1, y1) = to_screen(i - 1, stats[i - 1].mean_fitness);
+        let (x2, y2) = to_screen(i, stats[i].mean_fitness);
+        canvas.aa_line(x1, y1, x2, y2, mean_color)
+            .expect("Failed to draw mean fitness");
+
+        let (x1, y1) = to_screen(i - 1, stats[i - 1].best_fitness);
+        let (x2, y2) = to_screen(i, stats[i].best_fitness);
+        canvas.aa_line(x1, y1, x2, y2, best_color)
+            .expect("Failed to draw best fitness");
+    }
+}
@@ -0,0 +1,156 @@
+use std::io;
+
+use crate::network::*;
+
+#[derive(Debug, Clone)]
+pub struct HallOfFameEntry {
+    // Generation the genome was first seen in
+    pub generation: u32,
+    pub network: Network,
+}
+
+// The fittest distinct genomes seen over the whole run, best first. Kept apart
+// from the population so mutation can never lose them.
+#[derive(Debug, Clone)]
+pub struct HallOfFame {
+    pub capacity: usize,
+    pub entries: Vec<HallOfFameEntry>,
+}
+
+impl HallOfFame {
+    pub fn new(capacity: usize) -> Self {
+        HallOfFame {
+            capacity,
+            entries: Vec::new(),
+        }
+    }
+
+    // Fittest network ever seen
+    pub fn best(&self) -> Option<&Network> {
+        self.entries.first().map(|entry| &entry.network)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.len() == 0
+    }
+
+    // Offers every evaluated network to the archive. Copies of a genome that is
+    // already archived are ignored, so the first generation it appeared in is kept.
+    pub fn update(&mut self, generation: u32, pop: &[Network]) {
+        for network in pop {
+            let full = self.entries.len() >= self.capacity;
+            if full && self.entries.last().map_or(true, |worst| network.fitness <= worst.network.fitness) {
+                continue;
+            }
+            if self.entries.iter().any(|entry| same_genome(&entry.network, network)) {
+                continue;
+            }
+
+            let index = self.entries.iter().position(|entry| network.fitness > entry.network.fitness).unwrap_or(self.entries.len());
+            self.entries.insert(index, HallOfFameEntry { generation, network: network.clone() });
+            self.entries.truncate(self.capacity);
+        }
+    }
+
+    // An `hall_of_fame <capacity>` line, then each entry as an
+    // `entry <generation>` line followed by its genome
+    pub fn to_text(&self) -> String {
+        let mut output = format!("hall_of_fame {}\n", self.capacity);
+        for entry in &self.entries {
+            output += format!("entry {}\n", entry.generation).as_str();
+            output += entry.network.to_text().as_str();
+        }
+        output
+    }
+
+    pub fn from_text(text: &str) -> io::Result<HallOfFame> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid hall of fame line: {}", line));
+
+        let mut lines = text.lines().filter(|line| line.trim().len() != 0);
+        let header = lines.next().unwrap_or("");
+        let capacity = match header.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["hall_of_fame", capacity] => capacity.parse().map_err(|_| invalid(header))?,
+            _ => return Err(invalid(header)),
+        };
+
+        let mut hall_of_fame = HallOfFame::new(capacity);
+        let mut current: Option<(u32, String)> = None;
+        for line in lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields[0] == "entry" {
+                if let Some((generation, genome)) = current.take() {
+                    hall_of_fame.entries.push(HallOfFameEntry { generation, network: Network::from_text(&genome)? });
+                }
+                let generation = fields.get(1).and_then(|g| g.parse().ok()).ok_or_else(|| invalid(line))?;
+                current = Some((generation, String::new()));
+            } else {
+                match &mut current {
+                    Some((_, genome)) => {
+                        *genome += line;
+                        *genome += "\n";
+                    },
+                    None => return Err(invalid(line)),
+                }
+            }
+        }
+        if let Some((generation, genome)) = current {
+            hall_of_fame.entries.push(HallOfFameEntry { generation, network: Network::from_text(&genome)? });
+        }
+
+        Ok(hall_of_fame)
+    }
+}
+
+// Same structure and weights, whatever the id
+pub fn same_genome(a: &Network, b: &Network) -> bool {
+    a.nodes.len() == b.nodes.len()
+        && a.links.len() == b.links.len()
+        && a.links.iter().zip(&b.links).all(|(x, y)| {
+            x.inno_number == y.inno_number
+                && x.in_index == y.in_index
+                && x.out_index == y.out_index
+                && x.weight == y.weight
+                && x.enabled == y.enabled
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(fitness: f32, weight: f32) -> Network {
+        let mut n = Network::new(2, 1);
+        n.add_link(&mut 0, 0, 2, weight).unwrap();
+        n.fitness = fitness;
+        n
+    }
+
+    #[test]
+    fn keeps_the_best_distinct_genomes() {
+        let mut hall_of_fame = HallOfFame::new(2);
+        hall_of_fame.update(0, &[network(0.5, 1.0), network(0.2, 2.0)]);
+        hall_of_fame.update(1, &[network(0.5, 1.0), network(0.9, 3.0), network(0.1, 4.0)]);
+
+        let fitnesses: Vec<f32> = hall_of_fame.entries.iter().map(|e| e.network.fitness).collect();
+        let generations: Vec<u32> = hall_of_fame.entries.iter().map(|e| e.generation).collect();
+        assert_eq!(fitnesses, vec!(0.9, 0.5));
+        assert_eq!(generations, vec!(1, 0));
+        assert_eq!(hall_of_fame.best().unwrap().fitness, 0.9);
+    }
+
+    #[test]
+    fn text_round_trip_preserves_entries() {
+        let mut hall_of_fame = HallOfFame::new(3);
+        hall_of_fame.update(4, &[network(0.5, 1.0), network(0.7, -2.0)]);
+
+        let loaded = HallOfFame::from_text(&hall_of_fame.to_text()).unwrap();
+        assert_eq!(loaded.capacity, 3);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.entries[0].generation, 4);
+        assert!(same_genome(&loaded.entries[0].network, &hall_of_fame.entries[0].network));
+    }
+}
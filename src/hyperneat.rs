@@ -0,0 +1,167 @@
+use crate::error::NeatError;
+use crate::network::*;
+
+// Node coordinates of the network a CPPN is queried over. All coordinates
+// must have the same number of dimensions, usually 2 or 3.
+#[derive(Debug, Clone)]
+pub struct Substrate {
+    pub inputs: Vec<Vec<f32>>,
+    pub hidden: Vec<Vec<f32>>,
+    pub outputs: Vec<Vec<f32>>,
+}
+
+impl Substrate {
+    pub fn new(inputs: Vec<Vec<f32>>, hidden: Vec<Vec<f32>>, outputs: Vec<Vec<f32>>) -> Self {
+        Substrate {
+            inputs,
+            hidden,
+            outputs,
+        }
+    }
+
+    // Inputs along the bottom, hidden nodes across the middle and outputs along the top
+    pub fn layered(input_count: usize, hidden_count: usize, output_count: usize) -> Self {
+        Substrate::new(Substrate::row(input_count, -1.0), Substrate::row(hidden_count, 0.0), Substrate::row(output_count, 1.0))
+    }
+
+    // Evenly spaced points along x in [-1, 1] at the given y, handy for layered substrates
+    pub fn row(count: usize, y: f32) -> Vec<Vec<f32>> {
+        (0..count)
+            .map(|i| {
+                let x = if count == 1 { 0.0 } else { i as f32 / (count - 1) as f32 * 2.0 - 1.0 };
+                vec!(x, y)
+            })
+            .collect()
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.inputs.first().map_or(0, |c| c.len())
+    }
+
+    // CPPN inputs are the source and target coordinates followed by a bias input.
+    // Pass this to NEAT::new with a single output.
+    pub fn cppn_input_count(&self) -> u32 {
+        self.dimensions() as u32 * 2 + 1
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HyperNeat {
+    pub substrate: Substrate,
+    // CPPN outputs with a smaller magnitude produce no link. Set through
+    // with_weight_threshold, which keeps it in [0, 1).
+    weight_threshold: f32,
+    // Weight of a link whose CPPN output is 1 or -1
+    pub max_weight: f32,
+}
+
+impl HyperNeat {
+    pub fn new(substrate: Substrate) -> Self {
+        HyperNeat {
+            substrate,
+            weight_threshold: 0.2,
+            max_weight: 5.0,
+        }
+    }
+
+    pub fn with_weight_threshold(substrate: Substrate, weight_threshold: f32) -> Result<Self, NeatError> {
+        // Outputs above the threshold are rescaled by 1 - threshold
+        if !(0.0..1.0).contains(&weight_threshold) {
+            return Err(NeatError::InvalidWeightThreshold(weight_threshold));
+        }
+
+        Ok(HyperNeat {
+            weight_threshold,
+            ..HyperNeat::new(substrate)
+        })
+    }
+
+    pub fn query(&self, cppn: &Network, source: &[f32], target: &[f32]) -> f32 {
+        let mut inputs: Vec<f32> = source.iter().chain(target).cloned().collect();
+        inputs.push(1.0);
+        cppn.evaluate(inputs)[0]
+    }
+
+    // Builds the substrate network, linking inputs to hidden nodes and hidden
+    // nodes to outputs, or inputs straight to outputs if there are no hidden nodes
+    pub fn build(&self, cppn: &Network) -> Network {
+        let substrate = &self.substrate;
+        let mut n = Network::new(substrate.inputs.len() as u32, substrate.outputs.len() as u32);
+
+        let input_indexes: Vec<usize> = (0..substrate.inputs.len()).collect();
+        let output_indexes: Vec<usize> = (substrate.inputs.len()..substrate.inputs.len() + substrate.outputs.len()).collect();
+        let hidden_indexes: Vec<usize> = substrate.hidden.iter().map(|_| n.add_hidden_node()).collect();
+
+        let mut layers: Vec<(&Vec<Vec<f32>>, &Vec<usize>)> = vec!((&substrate.inputs, &input_indexes));
+        if hidden_indexes.len() != 0 {
+            layers.push((&substrate.hidden, &hidden_indexes));
+        }
+        layers.push((&substrate.outputs, &output_indexes));
+
+        // Links are pushed directly since the layers are already ordered, which
+        // avoids recomputing levels for every one of the many substrate links
+        let mut inno_number = 1;
+        for pair in layers.windows(2) {
+            let (sources, source_indexes) = pair[0];
+            let (targets, target_indexes) = pair[1];
+
+            for (source, &in_index) in sources.iter().zip(source_indexes) {
+                for (target, &out_index) in targets.iter().zip(target_indexes) {
+                    let output = self.query(cppn, source, target);
+                    if output.abs() <= self.weight_threshold {
+                        continue;
+                    }
+
+                    let scaled = (output.abs() - self.weight_threshold) / (1.0 - self.weight_threshold);
+                    let weight = output.signum() * scaled * self.max_weight;
+                    n.links.push(Link::new(inno_number, in_index, out_index, weight));
+                    inno_number += 1;
+                }
+            }
+        }
+
+        n.update_levels();
+        n
+    }
+
+    // Fitness adapter for evolving CPPNs with NEAT: scores the substrate network the CPPN builds
+    pub fn evaluate<F: FnMut(&Network) -> f32>(&self, cppn: &Network, mut fitness: F) -> f32 {
+        fitness(&self.build(cppn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_layered_substrate_network() {
+        let hyperneat = HyperNeat::new(Substrate::layered(3, 2, 1));
+
+        // A CPPN driven only by its bias gives the same strong weight everywhere
+        let mut inno = 1;
+        let mut cppn = Network::new(hyperneat.substrate.cppn_input_count(), 1);
+        cppn.add_link(&mut inno, 4, 5, 5.0).unwrap();
+
+        let n = hyperneat.build(&cppn);
+        assert_eq!(n.nodes.len(), 6);
+        // Every input to every hidden node, then both hidden nodes to the output
+        assert_eq!(n.links.len(), 3 * 2 + 2);
+        assert!(n.is_ordered());
+        assert!(n.links.iter().all(|l| l.weight > 0.0));
+
+        // A silent CPPN builds no links at all
+        let empty = hyperneat.build(&Network::new(hyperneat.substrate.cppn_input_count(), 1));
+        assert_eq!(empty.links.len(), 0);
+    }
+
+    #[test]
+    fn weight_threshold_must_leave_room_for_weights() {
+        for threshold in &[0.0, 0.5, 0.99] {
+            assert!(HyperNeat::with_weight_threshold(Substrate::layered(2, 0, 1), *threshold).is_ok());
+        }
+        for threshold in &[-0.1, 1.0, 2.0, std::f32::NAN] {
+            assert!(HyperNeat::with_weight_threshold(Substrate::layered(2, 0, 1), *threshold).is_err());
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use sdl2::event::Event;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::render::WindowCanvas;
+
+use crate::text::TextRenderer;
+
+// Input sliders and output bars for probing a loaded network
+//   Up / Down    select an input
+//   Left / Right nudge the selected input by 0.1
+//   Return       type a value for the selected input, Return again to apply
+//                or Escape to cancel; other keys are ignored while typing
+//   M            switch between manual inputs and inputs from the task
+// Sliders can also be dragged with the mouse.
+#[derive(Debug, Clone)]
+pub struct Inspector {
+    pub inputs: Vec<f32>,
+    pub selected: usize,
+    pub edit_buffer: Option<String>,
+    pub manual: bool,
+    dragging: bool,
+}
+
+impl Inspector {
+    // Slider layout in window coordinates
+    const X: f32 = 150.0;
+    const Y: f32 = 640.0;
+    const WIDTH: f32 = 400.0;
+    const ROW_HEIGHT: f32 = 30.0;
+    // Slider range
+    const MIN: f32 = -1.0;
+    const MAX: f32 = 1.0;
+
+    pub fn new(input_count: usize, manual: bool) -> Self {
+        Inspector {
+            inputs: vec![0.0; input_count],
+            selected: 0,
+            edit_buffer: None,
+            manual,
+            dragging: false,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::KeyDown { keycode: Some(keycode), .. } => self.handle_key(*keycode),
+            Event::TextInput { text, .. } => {
+                if let Some(buffer) = &mut self.edit_buffer {
+                    buffer.extend(text.chars().filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-'));
+                }
+            },
+            Event::MouseButtonDown { x, y, .. } => {
+                if let Some(row) = self.slider_at(*x, *y) {
+                    self.manual = true;
+                    self.selected = row;
+                    self.dragging = true;
+                    self.set_from_mouse(*x);
+                }
+            },
+            Event::MouseMotion { x, .. } => {
+                if self.dragging {
+                    self.set_from_mouse(*x);
+                }
+            },
+            Event::MouseButtonUp { .. } => self.dragging = false,
+            _ => {},
+        }
+    }
+
+    // Whether a typed value is being entered, during which Escape cancels it
+    pub fn editing(&self) -> bool {
+        self.edit_buffer.is_some()
+    }
+
+    fn handle_key(&mut self, keycode: Keycode) {
+        if self.inputs.len() == 0 {
+            return;
+        }
+
+        // Digits, '-' and '.' arrive as text input while typing
+        if let Some(buffer) = &mut self.edit_buffer {
+            match keycode {
+                Keycode::Backspace => {
+                    buffer.pop();
+                },
+                // Bad input is dropped and the old value kept
+                Keycode::Return => {
+                    if let Ok(value) = buffer.parse() {
+                        self.inputs[self.selected] = value;
+                    }
+                    self.edit_buffer = None;
+                },
+                Keycode::Escape => self.edit_buffer = None,
+                _ => {},
+            }
+            return;
+        }
+
+        match keycode {
+            Keycode::M => self.manual = !self.manual,
+            Keycode::Up => self.selected = (self.selected + self.inputs.len() - 1) % self.inputs.len(),
+            Keycode::Down => self.selected = (self.selected + 1) % self.inputs.len(),
+            Keycode::Left => self.nudge(-0.1),
+            Keycode::Right => self.nudge(0.1),
+            Keycode::Return => {
+                self.manual = true;
+                self.edit_buffer = Some(String::new());
+            },
+            _ => {},
+        }
+    }
+
+    // Moves the selected input along its slider, switching to manual inputs like dragging does
+    fn nudge(&mut self, amount: f32) {
+        self.manual = true;
+        let value = self.inputs[self.selected] + amount;
+        self.inputs[self.selected] = value.clamp(Inspector::MIN, Inspector::MAX);
+    }
+
+    fn slider_at(&self, x: i32, y: i32) -> Option<usize> {
+        let row = ((y as f32 - Inspector::Y) / Inspector::ROW_HEIGHT).floor();
+        let on_slider = x as f32 >= Inspector::X && x as f32 <= Inspector::X + Inspector::WIDTH;
+        if on_slider && row >= 0.0 && (row as usize) < self.inputs.len() {
+            Some(row as usize)
+        } else {
+            None
+        }
+    }
+
+    fn set_from_mouse(&mut self, x: i32) {
+        let t = ((x as f32 - Inspector::X) / Inspector::WIDTH).clamp(0.0, 1.0);
+        self.inputs[self.selected] = Inspector::MIN + t * (Inspector::MAX - Inspector::MIN);
+    }
+
+    // Draws a slider for every input and a bar for every output
+    pub fn draw(&self, canvas: &mut WindowCanvas, text: Option<&TextRenderer>, inputs: &[f32], outputs: &[f32]) {
+        let to_x = |value: f32| -> i16 {
+            let t = ((value - Inspector::MIN) / (Inspector::MAX - Inspector::MIN)).clamp(0.0, 1.0);
+            (Inspector::X + t * Inspector::WIDTH) as i16
+        };
+        let label_color = Color::RGB(220, 220, 220);
+
+        for (i, &value) in inputs.iter().enumerate() {
+            let row_y = (Inspector::Y + i as f32 * Inspector::ROW_HEIGHT + Inspector::ROW_HEIGHT / 2.0) as i16;
+            let color: (u8, u8, u8, u8) = if i == self.selected { (0, 255, 0, 255) } else { (0, 140, 0, 255) };
+
+            canvas.hline(Inspector::X as i16, (Inspector::X + Inspector::WIDTH) as i16, row_y, (100, 100, 100, 255))
+                .expect("Failed to draw slider");
+            canvas.filled_circle(to_x(value), row_y, 6, color)
+                .expect("Failed to draw slider knob");
+
+            if let Some(text) = text {
+                let value_str = match (&self.edit_buffer, i == self.selected) {
+                    (Some(buffer), true) => format!("{}_", buffer),
+                    _ => format!("{:.3}", value),
+                };
+                text.draw(canvas, format!("in {}", i).as_str(), 60, row_y as i32 - 8, label_color);
+                text.draw(canvas, value_str.as_str(), (Inspector::X + Inspector::WIDTH) as i32 + 20, row_y as i32 - 8, label_color);
+            }
+        }
+
+        let outputs_x = Inspector::X + Inspector::WIDTH + 150.0;
+        for (i, &value) in outputs.iter().enumerate() {
+            let row_y = Inspector::Y + i as f32 * Inspector::ROW_HEIGHT;
+            let zero_x = outputs_x + Inspector::WIDTH / 2.0;
+            let end_x = zero_x + value.clamp(-1.0, 1.0) * Inspector::WIDTH / 2.0;
+            let color: (u8, u8, u8, u8) = if value > 0.0 { (0, 0, 255, 255) } else { (255, 0, 0, 255) };
+
+            canvas.box_(zero_x.min(end_x) as i16, row_y as i16 + 5, zero_x.max(end_x) as i16, (row_y + Inspector::ROW_HEIGHT) as i16 - 5, color)
+                .expect("Failed to draw output bar");
+
+            if let Some(text) = text {
+                text.draw(canvas, format!("out {}: {:.4}", i, value).as_str(), (outputs_x + Inspector::WIDTH) as i32 + 10, row_y as i32 + 5, label_color);
+            }
+        }
+
+        if let Some(text) = text {
+            let mode = if self.manual { "Manual inputs (M for task)" } else { "Task inputs (M for manual)" };
+            text.draw(canvas, mode, 60, Inspector::Y as i32 - 30, label_color);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::genealogy::*;
+use crate::hall_of_fame::*;
+use crate::neat::*;
+use crate::network::*;
+
+use std::time::Instant;
+
+// Which islands send migrants to which
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    // Each island sends to the next one, the last back to the first
+    Ring,
+    // Each island sends to every other island
+    FullyConnected,
+}
+
+impl Topology {
+    pub fn from_name(name: &str) -> Option<Topology> {
+        match name {
+            "ring" => Some(Topology::Ring),
+            "full" => Some(Topology::FullyConnected),
+            _ => None,
+        }
+    }
+
+    // Islands receiving migrants from the given island
+    pub fn targets(&self, source: usize, island_count: usize) -> Vec<usize> {
+        match self {
+            Topology::Ring if island_count > 1 => vec!((source + 1) % island_count),
+            Topology::Ring => Vec::new(),
+            Topology::FullyConnected => (0..island_count).filter(|&i| i != source).collect(),
+        }
+    }
+}
+
+// Independent populations trained side by side, swapping their best genomes
+// every few generations. Innovation numbers and network ids are shared, so a
+// gene means the same thing on every island.
+#[derive(Debug, Clone)]
+pub struct Islands {
+    pub islands: Vec<NEAT>,
+    pub topology: Topology,
+    // Generations between migrations
+    pub migration_interval: u32,
+    // Genomes each island sends to each of its targets
+    pub migrant_count: usize,
+    pub generation: u32,
+
+    global_inno_number: u32,
+    next_network_id: u64,
+}
+
+impl Islands {
+    // The islands may use different population sizes and hyperparameters, but
+    // must share input and output counts
+    pub fn new(mut islands: Vec<NEAT>, topology: Topology) -> Self {
+        let global_inno_number = islands.iter().map(|neat| neat.global_inno_number).max().unwrap_or(1);
+        let mut next_network_id = 1;
+
+        // Every island numbers its initial networks from 1, so renumber them
+        for neat in &mut islands {
+            neat.genealogy = Genealogy::new();
+            for n in &mut neat.pop {
+                n.id = next_network_id;
+                next_network_id += 1;
+                neat.genealogy.record(n);
+            }
+        }
+
+        Islands {
+            islands,
+            topology,
+            migration_interval: 10,
+            migrant_count: 2,
+            generation: 0,
+
+            global_inno_number,
+            next_network_id,
+        }
+    }
+
+    // Runs one generation on every island. When the schedule says so, migrants
+    // are swapped between the evaluated populations before any island speciates.
+    pub fn train_with<F: FnMut(&Network) -> f32>(&mut self, mut fitness: F) {
+        let generation_start = Instant::now();
+        for neat in &mut self.islands {
+            neat.evaluate_generation(|n| n.fitness = fitness(n));
+        }
+
+        self.generation += 1;
+        if self.migration_interval != 0 && self.generation % self.migration_interval == 0 {
+            self.migrate();
+        }
+
+        for neat in &mut self.islands {
+            neat.global_inno_number = self.global_inno_number;
+            neat.next_network_id = self.next_network_id;
+
+            neat.reproduce(generation_start);
+
+            self.global_inno_number = neat.global_inno_number;
+            self.next_network_id = neat.next_network_id;
+        }
+    }
+
+    // Copies each island's fittest distinct genomes of the current generation over
+    // the least fit networks of its targets. Must run on evaluated populations,
+    // between evaluate_generation and reproduce. Genomes a target already has are
+    // not sent again.
+    pub fn migrate(&mut self) {
+        let island_count = self.islands.len();
+        let migrants: Vec<Vec<Network>> = self.islands.iter()
+            .map(|neat| {
+                let mut order: Vec<usize> = (0..neat.pop.len()).collect();
+                order.sort_by(|&a, &b| compare_scores(neat.pop[b].fitness, neat.pop[a].fitness));
+
+                let mut migrants: Vec<Network> = Vec::new();
+                for i in order {
+                    if migrants.len() == self.migrant_count {
+                        break;
+                    }
+                    if !migrants.iter().any(|m| same_genome(m, &neat.pop[i])) {
+                        migrants.push(neat.pop[i].clone());
+                    }
+                }
+                migrants
+            })
+            .collect();
+
+        for (source, sent) in migrants.iter().enumerate() {
+            for target in self.topology.targets(source, island_count) {
+                for migrant in sent {
+                    let neat = &mut self.islands[target];
+                    if neat.pop.iter().any(|n| same_genome(n, migrant)) {
+                        continue;
+                    }
+
+                    let worst = match (0..neat.pop.len()).min_by(|&a, &b| compare_scores(neat.pop[a].fitness, neat.pop[b].fitness)) {
+                        Some(worst) => worst,
+                        None => continue,
+                    };
+
+                    // The migrant keeps the fitness it was given on its home island
+                    let mut n = migrant.clone();
+                    n.id = self.next_network_id;
+                    self.next_network_id += 1;
+                    n.lineage = Lineage {
+                        parents: vec!(migrant.id),
+                        generation: neat.generation,
+                        species: None,
+                        mutations: Vec::new(),
+                    };
+                    neat.genealogy.record(&n);
+                    neat.genealogy.record_fitness(n.id, n.fitness);
+                    neat.pop[worst] = n;
+                }
+            }
+        }
+    }
+
+    // Fittest network any island has ever found, with the index of that island
+    pub fn best(&self) -> Option<(usize, &Network)> {
+        self.islands.iter()
+            .enumerate()
+            .filter_map(|(index, neat)| neat.hall_of_fame.best().map(|n| (index, n)))
+            .min_by(|a, b| compare_scores(b.1.fitness, a.1.fitness))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topologies_pick_targets() {
+        assert_eq!(Topology::Ring.targets(2, 3), vec!(0));
+        assert_eq!(Topology::Ring.targets(0, 1), Vec::<usize>::new());
+        assert_eq!(Topology::FullyConnected.targets(1, 3), vec!(0, 2));
+    }
+
+    #[test]
+    fn migrants_are_the_current_best_and_join_speciation() {
+        let mut islands = Islands::new(vec!(NEAT::new(30, 3, 1), NEAT::new(30, 3, 1)), Topology::Ring);
+        islands.islands[0].pop[5].add_link(&mut 1, 0, 3, 2.0).unwrap();
+        let source_id = islands.islands[0].pop[5].id;
+
+        for neat in &mut islands.islands {
+            neat.evaluate_generation(|n| n.fitness = n.links.len() as f32);
+        }
+        islands.migrate();
+
+        // Only the linked genome is new to the second island
+        let migrants: Vec<&Network> = islands.islands[1].pop.iter().filter(|n| n.links.len() == 1).collect();
+        assert_eq!(migrants.len(), 1);
+        assert_eq!(migrants[0].fitness, 1.0);
+        assert_eq!(migrants[0].lineage.parents, vec!(source_id));
+
+        let neat = &mut islands.islands[1];
+        neat.reproduce(Instant::now());
+        let mut members: Vec<usize> = neat.species_list.iter().flatten().cloned().collect();
+        members.sort();
+        assert_eq!(members, (0..neat.pop.len()).collect::<Vec<usize>>());
+        assert!(neat.species_info.iter().any(|s| s.champion.as_ref().map_or(false, |c| c.links.len() == 1)));
+    }
+
+    #[test]
+    fn innovations_and_ids_are_shared() {
+        let mut first = NEAT::new(30, 3, 1);
+        let mut second = NEAT::new(40, 3, 1);
+        first.add_connection_chance = 0.5;
+        second.add_node_chance = 0.2;
+        second.add_connection_chance = 0.5;
+
+        let mut islands = Islands::new(vec!(first, second), Topology::Ring);
+        islands.migration_interval = 2;
+        for _ in 0..10 {
+            islands.train_with(evaluate_xor);
+        }
+
+        // An innovation number always names the same connection
+        let mut genes: Vec<(u32, usize, usize)> = Vec::new();
+        for neat in &islands.islands {
+            for n in &neat.pop {
+                for link in &n.links {
+                    match genes.iter().find(|gene| gene.0 == link.inno_number) {
+                        Some(gene) => assert_eq!((gene.1, gene.2), (link.in_index, link.out_index)),
+                        None => genes.push((link.inno_number, link.in_index, link.out_index)),
+                    }
+                }
+            }
+        }
+
+        // Networks on different islands never share an id
+        let first_ids: Vec<u64> = islands.islands[0].pop.iter().map(|n| n.id).collect();
+        assert!(islands.islands[1].pop.iter().all(|n| !first_ids.contains(&n.id)));
+        let (index, best) = islands.best().unwrap();
+        assert_eq!(islands.islands[index].hall_of_fame.best().unwrap().id, best.id);
+    }
+}
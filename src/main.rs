@@ -1,13 +1,49 @@
 extern crate sdl2;
 extern crate rand;
 
+mod error;
 mod network;
 mod neat;
+mod stats;
+mod graph;
+mod viewer;
+mod text;
+mod environment;
+mod cartpole;
+mod flappy;
+mod dataset;
+mod novelty;
+mod pareto;
+mod hyperneat;
+mod task;
+mod inspector;
+mod run;
+mod hall_of_fame;
+mod checkpoint;
+mod genealogy;
+mod islands;
+mod distributed;
+mod external;
+mod prune;
 
 use network::*;
 use neat::*;
+use graph::*;
+use viewer::*;
+use text::*;
+use task::*;
+use inspector::*;
+use run::*;
+use distributed::*;
+use external::*;
+use dataset::*;
+use islands::*;
+use hyperneat::*;
+use pareto::*;
 
 use sdl2::event::Event;
+use sdl2::EventPump;
+use sdl2::render::WindowCanvas;
 use sdl2::keyboard::Keycode;
 use sdl2::keyboard::KeyboardState;
 use sdl2::keyboard::Scancode;
@@ -15,7 +51,92 @@ use sdl2::mouse::{MouseState};
 use sdl2::pixels::Color;
 use rand::Rng;
 
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: neat [task]\n       neat replay <genome file> [task]\n       neat run <task> [--generations N] [--fitness F] [--seconds S] [--stagnation N] [--save FILE] [--checkpoint FILE] [--resume FILE] [--ancestry FILE.dot|FILE.json] [--stats FILE.csv] [--workers N] [--worker-timeout S] [--islands N] [--migration-interval N] [--topology ring|full] [--hyperneat HIDDEN [--weight-threshold T]] [--selection fitness|novelty|blend:W|pareto]\n       neat run external --command CMD --inputs N --outputs N [--timeout S] [options]\n       neat run dataset --csv FILE --input-columns A,B --target-columns C [--normalize none|minmax|zscore] [--normalize-targets none|minmax|zscore] [--metric mse|accuracy|cross-entropy] [options]\n       neat worker <task> <coordinator address>\n       neat prune <genome file> <output file>";
+
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    // Bounded runs without a window
+    if args.get(1).map(|a| a.as_str()) == Some("run") {
+        run_headless(&args[2..]);
+        return;
+    }
+
+    // Writes the smallest network that computes the same outputs as a saved genome
+    if args.get(1).map(|a| a.as_str()) == Some("prune") {
+        match (args.get(2), args.get(3)) {
+            (Some(input_path), Some(output_path)) => match Network::load(input_path.as_str()) {
+                Ok(genome) => {
+                    let (pruned, report) = genome.pruned();
+                    println!("{}", report);
+                    if let Err(e) = pruned.save(output_path.as_str()) {
+                        eprintln!("Failed to save pruned genome to {}: {}", output_path, e);
+                    }
+                },
+                Err(e) => eprintln!("Failed to load genome from {}: {}", input_path, e),
+            },
+            _ => eprintln!("{}", USAGE),
+        }
+        return;
+    }
+
+    // Evaluates genomes for a coordinator started with run --workers
+    if args.get(1).map(|a| a.as_str()) == Some("worker") {
+        match (args.get(2).and_then(|name| Task::from_name(name.as_str())), args.get(3)) {
+            (Some(task), Some(address)) => {
+                if let Err(e) = run_worker(address.as_str(), |n| task.fitness(n)) {
+                    eprintln!("Worker failed: {}", e);
+                }
+            },
+            _ => eprintln!("{}", USAGE),
+        }
+        return;
+    }
+
+    // Either train on a task or replay a saved genome against one
+    let (genome_path, task_name) = match args.get(1).map(|a| a.as_str()) {
+        Some("replay") => match args.get(2) {
+            Some(path) => (Some(path.clone()), args.get(3).cloned()),
+            None => {
+                eprintln!("{}", USAGE);
+                return;
+            },
+        },
+        _ => (None, args.get(1).cloned()),
+    };
+
+    let task_name = task_name.unwrap_or("xor".to_string());
+    let task = match Task::from_name(task_name.as_str()) {
+        Some(task) => task,
+        None => {
+            eprintln!("Unknown task {}, expected one of: {}", task_name, Task::NAMES.join(", "));
+            eprintln!("{}", USAGE);
+            return;
+        },
+    };
+
+    let genome = match &genome_path {
+        Some(path) => match Network::load(path.as_str()) {
+            Ok(genome) => Some(genome),
+            Err(e) => {
+                eprintln!("Failed to load genome from {}: {}", path, e);
+                return;
+            },
+        },
+        None => None,
+    };
+
+    if let Some(genome) = &genome {
+        if genome.input_count != task.input_count() || genome.output_count != task.output_count() {
+            eprintln!("Genome has {} inputs and {} outputs but {} needs {} and {}",
+                genome.input_count, genome.output_count, task_name, task.input_count(), task.output_count());
+            return;
+        }
+    }
+
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let window_size = (1280, 1280);
@@ -32,7 +153,390 @@ fn main() {
 
     let mut event_pump = sdl_context.event_pump().unwrap();
 
-    let mut neat = NEAT::new(50, 3, 1);
+    // Labels are skipped if SDL_ttf or the font can't be loaded
+    let ttf_context = match sdl2::ttf::init() {
+        Ok(ttf_context) => Some(ttf_context),
+        Err(e) => {
+            eprintln!("Failed to initialize SDL_ttf: {}", e);
+            None
+        },
+    };
+    let text = match &ttf_context {
+        Some(ttf_context) => match TextRenderer::new(ttf_context, &canvas, font_path().as_str(), FONT_SIZE) {
+            Ok(text) => Some(text),
+            Err(e) => {
+                eprintln!("Failed to load font {}: {}", font_path(), e);
+                None
+            },
+        },
+        None => None,
+    };
+
+    match genome {
+        Some(genome) => run_replay(&mut canvas, &mut event_pump, text.as_ref(), task, genome),
+        None => run_training(&mut canvas, &mut event_pump, text.as_ref(), task, window_size),
+    }
+}
+
+// A number of seconds for a command line option. Negative, infinite and absurdly
+// large values are rejected rather than handed to Duration, which panics on them.
+fn parse_seconds(value: &str) -> Option<Duration> {
+    match value.parse::<f64>() {
+        Ok(seconds) if seconds >= 0.0 && seconds <= std::u32::MAX as f64 => Some(Duration::from_secs_f64(seconds)),
+        _ => None,
+    }
+}
+
+fn run_headless(args: &[String]) {
+    // An external program or a CSV dataset scores the genomes instead of a built-in task
+    let external = args.get(0).map(|a| a.as_str()) == Some("external");
+    let dataset = args.get(0).map(|a| a.as_str()) == Some("dataset");
+    let task = match args.get(0).and_then(|name| Task::from_name(name.as_str())) {
+        Some(task) => Some(task),
+        None if external || dataset => None,
+        None => {
+            eprintln!("Expected a task, one of: {}, external, dataset", Task::NAMES.join(", "));
+            eprintln!("{}", USAGE);
+            return;
+        },
+    };
+
+    let mut termination = Termination::new();
+    let mut save_path: Option<String> = None;
+    let mut checkpoint_path: Option<String> = None;
+    let mut resume_path: Option<String> = None;
+    let mut ancestry_path: Option<String> = None;
+    let mut stats_path: Option<String> = None;
+    let mut worker_count: usize = 0;
+    let mut worker_timeout: Option<Duration> = None;
+    let mut command: Option<String> = None;
+    let mut external_timeout: Option<Duration> = None;
+    let mut input_count: Option<u32> = task.map(|task| task.input_count());
+    let mut output_count: Option<u32> = task.map(|task| task.output_count());
+    let mut csv_path: Option<String> = None;
+    let mut csv_options = CsvOptions {
+        input_columns: Vec::new(),
+        target_columns: Vec::new(),
+        normalize_inputs: Normalization::None,
+        normalize_targets: Normalization::None,
+    };
+    let mut metric = Metric::Mse;
+    let mut island_count: usize = 0;
+    let mut migration_interval: Option<u32> = None;
+    let mut topology = Topology::Ring;
+    let mut hidden_count: Option<usize> = None;
+    let mut weight_threshold: Option<f32> = None;
+    let mut selection = SelectionMode::Fitness;
+
+    let mut i = 1;
+    while i < args.len() {
+        let value = args.get(i + 1).map(|v| v.as_str()).unwrap_or("");
+        let parsed = match args[i].as_str() {
+            "--generations" => value.parse().map(|v| termination.max_generations = Some(v)).is_ok(),
+            "--fitness" => value.parse().map(|v| termination.fitness_threshold = Some(v)).is_ok(),
+            "--seconds" => parse_seconds(value).map(|v| termination.time_budget = Some(v)).is_some(),
+            "--stagnation" => value.parse().map(|v| termination.stagnation_limit = Some(v)).is_ok(),
+            "--save" => {
+                save_path = Some(value.to_string());
+                value.len() != 0
+            },
+            "--checkpoint" => {
+                checkpoint_path = Some(value.to_string());
+                value.len() != 0
+            },
+            "--resume" => {
+                resume_path = Some(value.to_string());
+                value.len() != 0
+            },
+            "--stats" => {
+                stats_path = Some(value.to_string());
+                value.len() != 0
+            },
+            "--workers" => value.parse().map(|v| worker_count = v).is_ok(),
+            // Sockets refuse a zero read timeout
+            "--worker-timeout" => parse_seconds(value).filter(|v| *v != Duration::from_secs(0)).map(|v| worker_timeout = Some(v)).is_some(),
+            "--command" if external => {
+                command = Some(value.to_string());
+                value.len() != 0
+            },
+            "--timeout" if external => parse_seconds(value).map(|v| external_timeout = Some(v)).is_some(),
+            "--inputs" if external => value.parse().map(|v| input_count = Some(v)).is_ok(),
+            "--outputs" if external => value.parse().map(|v| output_count = Some(v)).is_ok(),
+            "--csv" if dataset => {
+                csv_path = Some(value.to_string());
+                value.len() != 0
+            },
+            "--input-columns" if dataset => {
+                csv_options.input_columns = value.split(',').map(|column| column.trim().to_string()).collect();
+                value.len() != 0
+            },
+            "--target-columns" if dataset => {
+                csv_options.target_columns = value.split(',').map(|column| column.trim().to_string()).collect();
+                value.len() != 0
+            },
+            "--normalize" if dataset => Normalization::from_name(value).map(|v| csv_options.normalize_inputs = v).is_some(),
+            "--normalize-targets" if dataset => Normalization::from_name(value).map(|v| csv_options.normalize_targets = v).is_some(),
+            "--metric" if dataset => Metric::from_name(value).map(|v| metric = v).is_some(),
+            "--islands" => value.parse().map(|v| island_count = v).is_ok(),
+            "--migration-interval" => value.parse().map(|v| migration_interval = Some(v)).is_ok(),
+            "--topology" => Topology::from_name(value).map(|v| topology = v).is_some(),
+            "--hyperneat" => value.parse().map(|v| hidden_count = Some(v)).is_ok(),
+            "--weight-threshold" => value.parse().map(|v| weight_threshold = Some(v)).is_ok(),
+            "--selection" => SelectionMode::from_name(value).map(|v| selection = v).is_some(),
+            "--ancestry" => {
+                ancestry_path = Some(value.to_string());
+                value.ends_with(".dot") || value.ends_with(".json")
+            },
+            _ => false,
+        };
+
+        if !parsed {
+            eprintln!("Invalid option {} {}", args[i], value);
+            eprintln!("{}", USAGE);
+            return;
+        }
+        i += 2;
+    }
+
+    // Islands are trained, checkpointed and recorded as separate populations
+    if island_count != 0 && (worker_count != 0 || checkpoint_path.is_some() || resume_path.is_some() || stats_path.is_some()) {
+        eprintln!("Islands cannot be combined with workers, --checkpoint, --resume or --stats");
+        eprintln!("{}", USAGE);
+        return;
+    }
+
+    // Only the built-in tasks report behaviours, and only to a single population in this process
+    let novelty = matches!(selection, SelectionMode::Novelty | SelectionMode::Blend(_));
+    if novelty && (task.is_none() || worker_count != 0 || island_count != 0 || hidden_count.is_some()) {
+        eprintln!("Novelty selection needs a built-in task and cannot use workers, islands or HyperNEAT");
+        eprintln!("{}", USAGE);
+        return;
+    }
+
+    // Pareto runs trade fitness against network size, on a single population in this process
+    if selection == SelectionMode::Pareto && (worker_count != 0 || island_count != 0) {
+        eprintln!("Pareto selection cannot use workers or islands");
+        eprintln!("{}", USAGE);
+        return;
+    }
+
+    // Class indexes lose their meaning once normalized
+    if metric != Metric::Mse && csv_options.normalize_targets != Normalization::None {
+        eprintln!("--normalize-targets cannot be used with a classification metric");
+        eprintln!("{}", USAGE);
+        return;
+    }
+
+    let data = match csv_path {
+        Some(path) => match Dataset::load_csv(path.as_str(), &csv_options).and_then(|data| metric.check(&data).map(|_| data)) {
+            Ok(data) if data.is_empty() => {
+                eprintln!("Dataset {} has no rows", path);
+                return;
+            },
+            Ok(data) => {
+                input_count = Some(data.input_count());
+                output_count = Some(metric.output_count(&data));
+                Some(data)
+            },
+            Err(e) => {
+                eprintln!("Failed to load dataset {}: {}", path, e);
+                return;
+            },
+        },
+        None if dataset => {
+            eprintln!("A dataset run needs --csv, --input-columns and --target-columns");
+            eprintln!("{}", USAGE);
+            return;
+        },
+        None => None,
+    };
+
+    let (input_count, output_count) = match (input_count, output_count) {
+        (Some(inputs), Some(outputs)) => (inputs, outputs),
+        _ => {
+            eprintln!("An external run needs --inputs and --outputs");
+            eprintln!("{}", USAGE);
+            return;
+        },
+    };
+
+    // With HyperNEAT the population holds CPPNs, which are scored on the
+    // layered substrate network they build for the task
+    let hyperneat = match hidden_count {
+        Some(hidden) => {
+            let substrate = Substrate::layered(input_count as usize, hidden, output_count as usize);
+            match weight_threshold.map(|threshold| HyperNeat::with_weight_threshold(substrate.clone(), threshold)) {
+                Some(Ok(hyperneat)) => Some(hyperneat),
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    eprintln!("{}", USAGE);
+                    return;
+                },
+                None => Some(HyperNeat::new(substrate)),
+            }
+        },
+        None if weight_threshold.is_some() => {
+            eprintln!("--weight-threshold needs --hyperneat");
+            eprintln!("{}", USAGE);
+            return;
+        },
+        None => None,
+    };
+    let (genome_inputs, genome_outputs) = match &hyperneat {
+        Some(hyperneat) => (hyperneat.substrate.cppn_input_count(), 1),
+        None => (input_count, output_count),
+    };
+
+    let mut neat = match resume_path {
+        Some(path) => match NEAT::load_checkpoint(path.as_str()) {
+            Ok(neat) if neat.pop[0].input_count == genome_inputs && neat.pop[0].output_count == genome_outputs => neat,
+            Ok(_) => {
+                eprintln!("Checkpoint {} does not match the inputs and outputs of the task", path);
+                return;
+            },
+            Err(e) => {
+                eprintln!("Failed to load checkpoint {}: {}", path, e);
+                return;
+            },
+        },
+        None => NEAT::new(50, genome_inputs, genome_outputs),
+    };
+    neat.selection = selection;
+    neat.stats_csv_path = stats_path;
+
+    let result = if worker_count != 0 {
+        // Workers can only run the built-in tasks, which they look up by name
+        if task.is_none() || hyperneat.is_some() {
+            eprintln!("Only built-in tasks without HyperNEAT can use workers");
+            eprintln!("{}", USAGE);
+            return;
+        }
+
+        let program = std::env::current_exe().map(|path| path.to_string_lossy().to_string()).unwrap_or(std::env::args().next().unwrap());
+        match WorkerPool::spawn(program.as_str(), &["worker".to_string(), args[0].clone()], worker_count) {
+            Ok(mut pool) => {
+                if pool.worker_count() < worker_count {
+                    eprintln!("Only {} of {} workers started", pool.worker_count(), worker_count);
+                }
+                if let Some(timeout) = worker_timeout {
+                    pool.timeout = timeout;
+                }
+                neat.run_distributed(&termination, &mut pool)
+            },
+            Err(e) => {
+                eprintln!("Failed to start workers: {}", e);
+                return;
+            },
+        }
+    } else if let (true, Some(task)) = (novelty, task) {
+        neat.run_with_behavior(&termination, |n| task.evaluate_with_behavior(n))
+    } else {
+        let mut fitness: Box<dyn FnMut(&Network) -> f32> = match (task, data, command) {
+            (Some(task), _, _) => Box::new(move |n| task.fitness(n)),
+            (None, Some(data), _) => Box::new(move |n| metric.fitness(n, &data)),
+            (None, None, Some(command)) => {
+                let mut external = ExternalFitness::shell(command.as_str());
+                if let Some(timeout) = external_timeout {
+                    external.timeout = timeout;
+                }
+                Box::new(move |n| external.fitness(n))
+            },
+            (None, None, None) => {
+                eprintln!("An external run needs --command");
+                eprintln!("{}", USAGE);
+                return;
+            },
+        };
+
+        if let Some(hyperneat) = hyperneat.clone() {
+            fitness = Box::new(move |cppn| hyperneat.evaluate(cppn, &mut fitness));
+        }
+
+        if selection == SelectionMode::Pareto {
+            neat.run_with_objectives(&termination, |n| fitness_and_size(fitness(n), n))
+        } else if island_count != 0 {
+            let populations = (0..island_count).map(|_| NEAT::new(50, genome_inputs, genome_outputs)).collect();
+            let mut islands = Islands::new(populations, topology);
+            if let Some(interval) = migration_interval {
+                islands.migration_interval = interval;
+            }
+
+            let result = islands.run(&termination, fitness);
+            // Report the hall of fame and ancestry of the island the champion came from
+            if result.is_ok() {
+                let index = islands.best().map_or(0, |(index, _)| index);
+                println!("Champion found on island {} of {}", index + 1, island_count);
+                neat = islands.islands.swap_remove(index);
+            }
+            result
+        } else {
+            neat.run(&termination, fitness)
+        }
+    };
+
+    match result {
+        Ok(result) => {
+            println!("{}", result);
+            if let Some(path) = save_path {
+                // A CPPN is saved as the substrate network it builds, so it can be replayed on the task
+                let saved = match &hyperneat {
+                    Some(hyperneat) => {
+                        let mut n = hyperneat.build(&result.champion);
+                        n.id = result.champion.id;
+                        n.fitness = result.champion.fitness;
+                        n
+                    },
+                    None => result.champion.clone(),
+                };
+                if let Err(e) = saved.save(path.as_str()) {
+                    eprintln!("Failed to save champion to {}: {}", path, e);
+                }
+            }
+
+            if selection == SelectionMode::Pareto {
+                println!("Pareto front of fitness and enabled links:");
+                for n in &neat.pareto_front {
+                    println!("  {}: fitness {}, {} links", n.id, n.objectives[0], -n.objectives[1]);
+                }
+            }
+
+            if !neat.hall_of_fame.is_empty() {
+                println!("Hall of fame ({} genomes):", neat.hall_of_fame.len());
+                for entry in &neat.hall_of_fame.entries {
+                    println!("  {} (generation {}): fitness {}", entry.network.id, entry.generation, entry.network.fitness);
+                }
+            }
+
+            if let Some(path) = ancestry_path {
+                let ancestry = if path.ends_with(".json") {
+                    neat.genealogy.to_json(result.champion.id)
+                } else {
+                    neat.genealogy.to_dot(result.champion.id)
+                };
+                if let Err(e) = std::fs::write(path.as_str(), ancestry) {
+                    eprintln!("Failed to save ancestry to {}: {}", path, e);
+                }
+            }
+
+            if let Some(path) = checkpoint_path {
+                if let Err(e) = neat.save_checkpoint(path.as_str()) {
+                    eprintln!("Failed to save checkpoint to {}: {}", path, e);
+                }
+            }
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+        },
+    }
+}
+
+fn run_training(canvas: &mut WindowCanvas, event_pump: &mut EventPump, text: Option<&TextRenderer>, task: Task, window_size: (u32, u32)) {
+    let mut neat = NEAT::new(50, task.input_count(), task.output_count());
+    let mut viewer = Viewer::new();
+
+    // Replay of the latest champion playing the task, restarted whenever it ends
+    let mut replay: Option<(Replay, Network)> = None;
 
     'running: loop {
         for event in event_pump.poll_iter() {
@@ -41,19 +545,112 @@ fn main() {
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
                    break 'running; 
                 },
+                Event::KeyDown { keycode: Some(keycode), .. } => {
+                    viewer.handle_key(keycode, &neat);
+                },
                 _ => {}
             }
         }
 
-        neat.train();
+        if viewer.should_train() {
+            neat.train_with(|n| task.fitness(n));
 
-        let top = &neat.pop[0];
-        println!("{}", top.fitness);
+            if let Some(stats) = neat.stats.last() {
+                println!("{}", stats);
+            }
+        }
+
+        let top = viewer.displayed(&neat);
 
         canvas.set_draw_color(Color::from((0, 0, 0)));
         canvas.clear();
-        top.draw(&mut canvas, 50.0, 50.0, 1280.0, 500.0);
+
+        match viewer.view {
+            ViewMode::Network => {
+                // Leave the right half for the replay when there is one
+                let network_width = if task.replay().is_some() { 560.0 } else { 1180.0 };
+
+                let finished = replay.as_ref().map_or(true, |(simulation, _)| simulation.done());
+                if finished {
+                    replay = match (task.replay(), neat.champion.clone()) {
+                        (Some(simulation), Some(champion)) => Some((simulation, champion)),
+                        _ => None,
+                    };
+                }
+
+                if let Some((simulation, champion)) = &mut replay {
+                    simulation.step(champion);
+                    simulation.draw(canvas, 680.0, 50.0, 550.0, 500.0);
+                }
+
+                // The browsed genome is drawn, saved and described by the HUD; the replay is labelled on its own
+                top.draw(canvas, 50.0, 50.0, network_width, 500.0);
+                draw_fitness_graph(canvas, &neat.stats, 50.0, 680.0, 1180.0, 550.0);
+
+                if let Some(text) = text {
+                    top.draw_labels(canvas, text, 50.0, 50.0, network_width, 500.0, viewer.show_weights);
+                    viewer.draw_hud(canvas, text, &neat, 10, 560);
+                    if let Some((_, champion)) = &replay {
+                        let label = format!("Replaying champion {} (fitness {:.4})", champion.id, champion.fitness);
+                        text.draw(canvas, label.as_str(), 680, 560, Color::RGB(255, 255, 255));
+                    }
+                }
+            },
+            ViewMode::SpeciesGrid => {
+                viewer.draw_species_grid(canvas, text, &neat, 0.0, 0.0, window_size.0 as f32, window_size.1 as f32);
+            },
+        }
+
         canvas.present();
     }
+}
+
+// Runs a saved genome live, either on the task's simulation or on inputs set by hand
+fn run_replay(canvas: &mut WindowCanvas, event_pump: &mut EventPump, text: Option<&TextRenderer>, task: Task, genome: Network) {
+    let mut simulation = task.replay();
+    let mut inspector = Inspector::new(genome.input_count as usize, simulation.is_none());
+
+    'running: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} => break 'running,
+                // Escape cancels a typed value before it quits
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } if !inspector.editing() => {
+                   break 'running; 
+                },
+                _ => inspector.handle_event(&event),
+            }
+        }
 
+        let inputs = match &simulation {
+            Some(sim) if !inspector.manual => sim.observe(),
+            _ => inspector.inputs.clone(),
+        };
+        let outputs = genome.evaluate(inputs.clone());
+
+        if let Some(sim) = &mut simulation {
+            if !inspector.manual {
+                sim.apply(&outputs);
+                if sim.done() {
+                    *sim = task.replay().unwrap();
+                }
+            }
+        }
+
+        canvas.set_draw_color(Color::from((0, 0, 0)));
+        canvas.clear();
+
+        let network_width = if simulation.is_some() { 560.0 } else { 1180.0 };
+        genome.draw(canvas, 50.0, 50.0, network_width, 500.0);
+        if let Some(sim) = &simulation {
+            sim.draw(canvas, 680.0, 50.0, 550.0, 500.0);
+        }
+        if let Some(text) = text {
+            genome.draw_labels(canvas, text, 50.0, 50.0, network_width, 500.0, true);
+        }
+        inspector.draw(canvas, text, &inputs, &outputs);
+
+        canvas.present();
+        thread::sleep(Duration::from_millis(16));
+    }
 }
@@ -1,6 +1,15 @@
+use crate::error::NeatError;
+use crate::genealogy::*;
+use crate::hall_of_fame::*;
 use crate::network::*;
+use crate::novelty::*;
+use crate::pareto::*;
+use crate::stats::*;
 use rand::Rng;
 
+use std::cmp::Ordering;
+use std::time::Instant;
+
 // Helper Functions
 pub fn random() -> f32 {
     let f: f32 = rand::thread_rng().gen();
@@ -18,7 +27,18 @@ pub fn random_bool() -> bool {
     weighted_bool(0.5)
 }
 
-pub fn evaluate_xor(mut n: Network) -> f32 {
+// Total order on scores with NaN below every number, so a broken evaluation
+// can neither abort a sort nor rank first
+pub fn compare_scores(a: f32, b: f32) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.partial_cmp(&b).unwrap(),
+    }
+}
+
+pub fn evaluate_xor(n: &Network) -> f32 {
     let mut error = 0f32;
 
     let actual: Vec<f32> = vec!(
@@ -41,10 +61,49 @@ pub fn evaluate_xor(mut n: Network) -> f32 {
     return 1.0 - error;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionMode {
+    // Rank networks by their fitness
+    Fitness,
+    // Rank networks by how different their behaviour is from everything seen so far
+    Novelty,
+    // Rank by novelty_weight * novelty + (1 - novelty_weight) * fitness, both scaled to [0, 1]
+    Blend(f32),
+    // Rank networks by non-dominated front, then crowding distance, over their objectives
+    Pareto,
+}
+
+impl SelectionMode {
+    // "fitness", "novelty", "pareto", or "blend:<novelty weight>" with the weight in [0, 1]
+    pub fn from_name(name: &str) -> Option<SelectionMode> {
+        match name {
+            "fitness" => Some(SelectionMode::Fitness),
+            "novelty" => Some(SelectionMode::Novelty),
+            "pareto" => Some(SelectionMode::Pareto),
+            _ => name.strip_prefix("blend:")
+                .and_then(|weight| weight.parse::<f32>().ok())
+                .filter(|weight| *weight >= 0.0 && *weight <= 1.0)
+                .map(SelectionMode::Blend),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Species {
+    pub id: u32,
+    // Generation the species first appeared in
+    pub created: u32,
+    // Best fitness any member has ever reached
+    pub best_fitness: f32,
+    // Fittest member of the last evaluated generation, before it was mutated
+    pub champion: Option<Network>,
+}
+
 #[derive(Debug, Clone)]
 pub struct NEAT {
     // Global Inno Number
     pub global_inno_number: u32,
+    pub next_network_id: u64,
 
     // Generation
     pub generation: u32,
@@ -53,16 +112,30 @@ pub struct NEAT {
     // Population
     pub pop: Vec<Network>,
     pub species_list: Vec<Vec<usize>>,
+    // Parallel to species_list
+    pub species_info: Vec<Species>,
+    pub next_species_id: u32,
 
     pub past_pop: Vec<Network>,
     pub past_species_list: Vec<Vec<usize>>,
 
+    // Best network of the last evaluated generation, before it was mutated
+    pub champion: Option<Network>,
+    // Networks of the last evaluated generation no other network beats on every
+    // objective, before they were mutated. Only kept for Pareto selection.
+    pub pareto_front: Vec<Network>,
+    // Fittest distinct networks of every generation so far
+    pub hall_of_fame: HallOfFame,
+    // Lineage of every network created so far
+    pub genealogy: Genealogy,
+
     // Mutations
     pub change_weights_chance: f32,
     pub perturb_weights_chance: f32,
     pub perturb_amount: f32,
     pub add_node_chance: f32,
     pub add_connection_chance: f32,
+    pub reenable_links: bool,
 
     // Measuring Coefficients
     pub c1: f32,
@@ -71,25 +144,46 @@ pub struct NEAT {
     pub distance_threshold: f32,
 
     pub preserve_champion_threshhold: u32,
+
+    // Selection
+    pub selection: SelectionMode,
+    pub novelty_archive: NoveltyArchive,
+
+    // Statistics
+    pub stats: Vec<GenerationStats>,
+    pub stats_csv_path: Option<String>,
+    pub start_time: Instant,
 }
 
 impl NEAT {
+    // Best networks copied unchanged into every new generation, so populations
+    // need at least this many members
+    pub const ELITE_COUNT: usize = 20;
+
     pub fn new(pop_size: u32, num_inputs: u32, num_outputs: u32) -> Self {
         let mut pop: Vec<Network> = Vec::new();
 
 
         // Initialize population
-        for _ in 0..pop_size {
+        for i in 0..pop_size {
             let mut inno_number: u32 = 0;
             let mut n = Network::new(num_inputs, num_outputs);
+            n.id = i as u64 + 1;
+            n.lineage.species = Some(1);
             //n.add_link(&mut inno_number, 0, num_inputs as usize + 1, 0.0);
             //n.mutate_add_connection();
             //n.connections[0].weight = 0.0;
             pop.push(n.clone());
         }
 
+        let mut genealogy = Genealogy::new();
+        for n in &pop {
+            genealogy.record(n);
+        }
+
         NEAT {
             global_inno_number: 1,
+            next_network_id: pop_size as u64 + 1,
 
             generation: 0,
             pop_size,
@@ -98,14 +192,22 @@ impl NEAT {
             past_pop: Vec::new(),
 
             species_list: vec!((0..pop_size as usize).collect()),
+            species_info: vec!(Species { id: 1, created: 0, best_fitness: std::f32::MIN, champion: None }),
+            next_species_id: 2,
             past_species_list: Vec::new(),
 
+            champion: None,
+            pareto_front: Vec::new(),
+            hall_of_fame: HallOfFame::new(10),
+            genealogy,
+
             // Mutations
             change_weights_chance: 0.8,
             perturb_weights_chance: 0.9,
             perturb_amount: 0.5,
             add_node_chance: 0.03,
             add_connection_chance: 0.05,
+            reenable_links: false,
 
             // Measuring Coefficients
             c1: 1.0,
@@ -114,52 +216,249 @@ impl NEAT {
             distance_threshold: 3.0,
 
             preserve_champion_threshhold: 5,
+
+            selection: SelectionMode::Fitness,
+            novelty_archive: NoveltyArchive::new(),
+
+            stats: Vec::new(),
+            stats_csv_path: None,
+            start_time: Instant::now(),
+        }
+    }
+
+    // Compatibility distance from the NEAT paper: c1 * E / N + c2 * D / N + c3 * W
+    pub fn get_distance(&self, a: &Network, b: &Network) -> f32 {
+        let a_max = a.links.iter().map(|l| l.inno_number).max().unwrap_or(0);
+        let b_max = b.links.iter().map(|l| l.inno_number).max().unwrap_or(0);
+
+        let mut excess = 0;
+        let mut disjoint = 0;
+        let mut matching = 0;
+        let mut weight_difference = 0.0;
+
+        for link in &a.links {
+            match b.links.iter().find(|other| other.inno_number == link.inno_number) {
+                Some(other) => {
+                    matching += 1;
+                    weight_difference += (link.weight - other.weight).abs();
+                },
+                None if link.inno_number > b_max => excess += 1,
+                None => disjoint += 1,
+            }
+        }
+
+        for link in &b.links {
+            if !a.links.iter().any(|other| other.inno_number == link.inno_number) {
+                if link.inno_number > a_max {
+                    excess += 1;
+                } else {
+                    disjoint += 1;
+                }
+            }
         }
+
+        // Small genomes are not normalized
+        let gene_count = a.links.len().max(b.links.len());
+        let n = if gene_count < 20 { 1.0 } else { gene_count as f32 };
+        let w = if matching == 0 { 0.0 } else { weight_difference / matching as f32 };
+
+        self.c1 * excess as f32 / n + self.c2 * disjoint as f32 / n + self.c3 * w
     }
 
-    pub fn get_distance(a: &Network, b: &Network) -> f32 {
-        return 0.0;
+    fn sort_for_selection(&mut self) {
+        let novelty_weight = match self.selection {
+            SelectionMode::Fitness => {
+                self.pop.sort_by(|a, b| compare_scores(b.fitness, a.fitness));
+                return;
+            },
+            SelectionMode::Pareto => {
+                let objectives: Vec<Vec<f32>> = self.pop.iter().map(|n| n.objectives.clone()).collect();
+                let mut unsorted: Vec<Option<Network>> = self.pop.drain(..).map(Some).collect();
+                for i in pareto_order(&objectives) {
+                    self.pop.push(unsorted[i].take().unwrap());
+                }
+                return;
+            },
+            SelectionMode::Novelty => 1.0,
+            SelectionMode::Blend(weight) => weight,
+        };
+
+        // Scale both measures to [0, 1] so the weight means the same on every task
+        let range = |values: Vec<f32>| -> (f32, f32) {
+            let min = values.iter().cloned().fold(std::f32::MAX, f32::min);
+            let max = values.iter().cloned().fold(std::f32::MIN, f32::max);
+            (min, (max - min).max(std::f32::EPSILON))
+        };
+        let (min_fitness, fitness_range) = range(self.pop.iter().map(|n| n.fitness).collect());
+        let (min_novelty, novelty_range) = range(self.pop.iter().map(|n| n.novelty).collect());
+
+        let score = |n: &Network| -> f32 {
+            novelty_weight * (n.novelty - min_novelty) / novelty_range
+                + (1.0 - novelty_weight) * (n.fitness - min_fitness) / fitness_range
+        };
+
+        self.pop.sort_by(|a, b| compare_scores(score(b), score(a)));
     }
 
     pub fn train(&mut self) {
+        self.train_with(evaluate_xor);
+    }
+
+    // Runs one generation, scoring every network with the given fitness function
+    pub fn train_with<F: FnMut(&Network) -> f32>(&mut self, mut fitness: F) {
+        self.train_evaluated(|n| n.fitness = fitness(n));
+    }
+
+    // Runs one generation where the evaluation also reports a behaviour
+    // descriptor for novelty search
+    pub fn train_with_behavior<F: FnMut(&Network) -> (f32, Vec<f32>)>(&mut self, mut evaluate: F) {
+        self.train_evaluated(|n| {
+            let (fitness, behavior) = evaluate(n);
+            n.fitness = fitness;
+            n.behavior = behavior;
+        });
+    }
+
+    // Runs one generation where the evaluation reports several objectives to
+    // maximize. The first objective is also used as the network's fitness.
+    pub fn train_with_objectives<F: FnMut(&Network) -> Vec<f32>>(&mut self, mut evaluate: F) {
+        self.train_evaluated(|n| {
+            n.objectives = evaluate(n);
+            n.fitness = n.objectives.first().cloned().unwrap_or(0.0);
+        });
+    }
+
+    // Runs one generation, letting the closure fill in each network's scores
+    pub fn train_evaluated<F: FnMut(&mut Network)>(&mut self, evaluate: F) {
+        let generation_start = Instant::now();
+        self.evaluate_generation(evaluate);
+        self.reproduce(generation_start);
+    }
+
+    // First half of a generation: scores the population and records its champions
+    pub fn evaluate_generation<F: FnMut(&mut Network)>(&mut self, mut evaluate: F) {
         // Update Past Population
         self.past_pop = self.pop.clone();
         self.past_species_list = self.species_list.clone();
 
         // Evaluate all networks
         for i in 0..self.pop.len() {
-            self.pop[i].fitness = evaluate_xor(self.pop[i].clone());
+            evaluate(&mut self.pop[i]);
+            self.genealogy.record_fitness(self.pop[i].id, self.pop[i].fitness);
         } 
 
-        // Sort by Fitness
-        self.pop.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        // Score novelty
+        if let SelectionMode::Novelty | SelectionMode::Blend(_) = self.selection {
+            let behaviors: Vec<Vec<f32>> = self.pop.iter().map(|n| n.behavior.clone()).collect();
+            let scores = self.novelty_archive.update(&behaviors);
+            for i in 0..self.pop.len() {
+                self.pop[i].novelty = scores[i];
+            }
+        }
+
+        // Track the fittest network regardless of how selection ranks them
+        self.champion = self.pop.iter()
+            .min_by(|a, b| compare_scores(b.fitness, a.fitness))
+            .cloned();
+        self.hall_of_fame.update(self.generation, &self.pop);
+
+        if self.selection == SelectionMode::Pareto {
+            let objectives: Vec<Vec<f32>> = self.pop.iter().map(|n| n.objectives.clone()).collect();
+            self.pareto_front = match non_dominated_fronts(&objectives).first() {
+                Some(front) => front.iter().map(|&i| self.pop[i].clone()).collect(),
+                None => Vec::new(),
+            };
+        }
+    }
+
+    // Second half of a generation: speciates the evaluated population and breeds the next one
+    pub fn reproduce(&mut self, generation_start: Instant) {
+        // Sort for selection, best first
+        self.sort_for_selection();
 
         // ----- Speciate -----
         self.species_list = Vec::new();
+        let past_species_info = self.species_info.clone();
+        self.species_info = Vec::new();
         
         // Select species reps
-        let mut species_reps: Vec<usize> = Vec::new();
+        let mut species_reps: Vec<Network> = Vec::new();
         for i in 0..self.past_species_list.len() {
             let rep_index: usize = rand::thread_rng().gen_range(0, self.past_species_list[i].len());
-            species_reps.push(self.past_species_list[i][rep_index])
+            species_reps.push(self.past_pop[self.past_species_list[i][rep_index]].clone());
+            self.species_list.push(Vec::new());
+            self.species_info.push(past_species_info[i].clone());
+        }
+
+        // Place each network in the first species whose rep is close enough
+        for i in 0..self.pop.len() {
+            let species_index = species_reps.iter().position(|rep| self.get_distance(rep, &self.pop[i]) < self.distance_threshold);
+            match species_index {
+                Some(index) => self.species_list[index].push(i),
+                None => {
+                    species_reps.push(self.pop[i].clone());
+                    self.species_list.push(vec!(i));
+                    self.species_info.push(Species { id: self.next_species_id, created: self.generation, best_fitness: std::f32::MIN, champion: None });
+                    self.next_species_id += 1;
+                },
+            }
+        }
+
+        // Drop extinct species and update the survivors' best fitness
+        let mut i = 0;
+        while i < self.species_list.len() {
+            if self.species_list[i].len() == 0 {
+                self.species_list.remove(i);
+                self.species_info.remove(i);
+            } else {
+                let champion = self.species_list[i].iter()
+                    .map(|&j| &self.pop[j])
+                    .min_by(|a, b| compare_scores(b.fitness, a.fitness))
+                    .cloned();
+                let champion_fitness = champion.as_ref().map_or(std::f32::MIN, |n| n.fitness);
+                self.species_info[i].best_fitness = self.species_info[i].best_fitness.max(champion_fitness);
+                self.species_info[i].champion = champion;
+                i += 1;
+            }
         }
 
-        let elite = 20;
+        // Record statistics of the evaluated population; timings are filled in once reproduction is done
+        let mut stats = GenerationStats::collect(self.generation, &self.pop, &self.species_list, generation_start.elapsed(), self.start_time.elapsed());
+
+        // Species each network belongs to, for the lineage of its offspring
+        let mut species_of: Vec<Option<u32>> = vec![None; self.pop.len()];
+        for (members, info) in self.species_list.iter().zip(&self.species_info) {
+            for &i in members {
+                species_of[i] = Some(info.id);
+            }
+        }
+
+        // Each slot remembers the network it descends from
+        let mut parents: Vec<usize> = (0..self.pop.len()).collect();
+
+        let elite = NEAT::ELITE_COUNT;
         for i in 0..elite {
             self.pop[self.pop_size as usize - elite + i] = self.pop[i].clone();
+            parents[self.pop_size as usize - elite + i] = i;
         }
 
+        let parent_ids: Vec<u64> = parents.iter().map(|&i| self.pop[i].id).collect();
+
         // Mutations
-        for network in &mut self.pop {
+        for (index, network) in self.pop.iter_mut().enumerate() {
+            let mut mutations: Vec<Mutation> = Vec::new();
+
             // Change weights
             if weighted_bool(self.change_weights_chance) {
                 if weighted_bool(self.perturb_weights_chance) {
+                    mutations.push(Mutation::PerturbWeights);
                     for i in 0..network.links.len() {
                         let amount = random() * self.perturb_amount - (0.5 * self.perturb_amount);
                         network.links[i].weight += amount;
                     }
                 } else {
                     // Randomize weights
+                    mutations.push(Mutation::RandomizeWeights);
                     for i in 0..network.links.len() {
                         network.links[i].weight = random() * 10.0 - 5.0;
                     }
@@ -167,16 +466,139 @@ impl NEAT {
             }
 
             // Add node/connection
+            // A network with nothing to split or connect just skips the mutation
             if weighted_bool(self.add_node_chance) {
-                network.add_random_node(&mut self.global_inno_number);
+                match network.add_random_node(&mut self.global_inno_number) {
+                    Ok(()) => mutations.push(Mutation::AddNode { node: network.nodes.len() - 1, inno: self.global_inno_number - 2 }),
+                    Err(NeatError::NoLinksAvailable) => {},
+                    Err(e) => eprintln!("Failed to add a node to network {}: {}", network.id, e),
+                }
             }
 
             if weighted_bool(self.add_connection_chance) {
-                network.add_random_link(&mut self.global_inno_number).expect("No nodes available");
+                let disabled: Vec<u32> = network.links.iter().filter(|l| !l.enabled).map(|l| l.inno_number).collect();
+                let inno_before = self.global_inno_number;
+                match network.add_random_link(&mut self.global_inno_number, self.reenable_links) {
+                    Ok(()) => {
+                        if self.global_inno_number != inno_before {
+                            mutations.push(Mutation::AddLink { inno: inno_before });
+                        } else if let Some(link) = network.links.iter().find(|l| l.enabled && disabled.contains(&l.inno_number)) {
+                            mutations.push(Mutation::ReenableLink { inno: link.inno_number });
+                        }
+                    },
+                    Err(NeatError::FullyConnected) => {},
+                    Err(e) => eprintln!("Failed to add a link to network {}: {}", network.id, e),
+                }
+            }
+
+            // A changed genome or an elite copy is a new individual
+            if mutations.len() != 0 || parents[index] != index {
+                network.id = self.next_network_id;
+                self.next_network_id += 1;
+                network.lineage = Lineage {
+                    parents: vec!(parent_ids[index]),
+                    generation: self.generation + 1,
+                    species: species_of[parents[index]],
+                    mutations,
+                };
+                self.genealogy.record(network);
+            }
+        }
+
+        stats.elapsed = generation_start.elapsed();
+        stats.total_elapsed = self.start_time.elapsed();
+        if let Some(path) = &self.stats_csv_path {
+            if let Err(e) = stats.append_csv(path) {
+                eprintln!("Failed to write statistics to {}: {}", path, e);
             }
         }
+        self.stats.push(stats);
 
         self.generation += 1;
 
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn training_skips_structural_mutations_with_nothing_to_change() {
+        // A single disabled link leaves nothing to split, and with re-enabling
+        // off the only pair of nodes counts as connected
+        let mut neat = NEAT::new(30, 1, 1);
+        for n in &mut neat.pop {
+            n.add_link(&mut 1, 0, 1, 1.0).unwrap();
+            n.links[0].enabled = false;
+        }
+        neat.global_inno_number = 2;
+        neat.add_node_chance = 1.0;
+        neat.add_connection_chance = 1.0;
+
+        for _ in 0..3 {
+            neat.train_with(|n| n.links[0].weight);
+        }
+
+        assert_eq!(neat.generation, 3);
+        assert_eq!(neat.global_inno_number, 2);
+        assert!(neat.pop.iter().all(|n| n.nodes.len() == 2 && n.links.len() == 1 && !n.links[0].enabled));
+    }
+
+    #[test]
+    fn training_records_the_lineage_of_each_offspring() {
+        // Networks 41 to 50 are fully connected and far from the empty ones, so
+        // the two groups form separate species and only the empty one can mutate
+        let full = |id: u64| id > 40;
+        let mut neat = NEAT::new(50, 4, 1);
+        for n in &mut neat.pop {
+            if full(n.id) {
+                for input in 0..4 {
+                    n.add_link(&mut (input as u32 + 1), input, 4, 1.0).unwrap();
+                }
+            }
+        }
+        neat.global_inno_number = 5;
+        neat.change_weights_chance = 0.0;
+        neat.add_node_chance = 0.0;
+        neat.add_connection_chance = 1.0;
+
+        neat.train_with(|n| n.id as f32);
+
+        // The fittest 20 networks, 50 down to 31, are copied over the least fit slots
+        let elite = NEAT::ELITE_COUNT;
+        for i in 0..elite {
+            let copy = &neat.pop[50 - elite + i];
+            assert_eq!(copy.lineage.parents, vec!(50 - i as u64));
+            assert_eq!(copy.lineage.generation, 1);
+            assert!(copy.id > 50);
+        }
+
+        let species_of = |id: u64| neat.species_info.iter()
+            .find(|info| info.champion.as_ref().map_or(false, |c| full(c.id) == full(id)))
+            .map(|info| info.id);
+        assert_ne!(species_of(1), species_of(50));
+
+        for n in &neat.pop {
+            let parent = match n.lineage.parents.first() {
+                Some(&parent) => parent,
+                // Survivors that did not change keep their original identity
+                None => {
+                    assert!(full(n.id) && n.id <= 50);
+                    continue;
+                },
+            };
+
+            if full(parent) {
+                assert_eq!(n.lineage.mutations, Vec::new());
+            } else {
+                match n.lineage.mutations.as_slice() {
+                    [Mutation::AddLink { inno }] => assert!(n.links.iter().any(|l| l.inno_number == *inno)),
+                    mutations => panic!("Unexpected mutations {:?}", mutations),
+                }
+            }
+            assert_eq!(n.lineage.species, species_of(parent));
+            assert_eq!(neat.genealogy.get(n.id).map(|ancestor| &ancestor.lineage), Some(&n.lineage));
+        }
+    }
+}
@@ -1,10 +1,18 @@
 use std::fmt;
 use std::fmt::{Formatter, Display};
+use std::fs;
+use std::io;
 use std::result::Result;
 use rand::Rng;
 
+use crate::error::NeatError;
+use crate::genealogy::*;
+
 use sdl2::render::WindowCanvas;
 use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::pixels::Color;
+
+use crate::text::TextRenderer;
 
 use std::cmp::*;
 
@@ -115,11 +123,21 @@ impl Display for Link {
 
 #[derive(Debug, Clone)]
 pub struct Network {
+    pub id: u64,
     pub nodes: Vec<Node>,
     pub links: Vec<Link>,
     pub input_count: u32,
     pub output_count: u32,
     pub fitness: f32,
+
+    // Behaviour descriptor and its novelty, used by novelty search
+    pub behavior: Vec<f32>,
+    pub novelty: f32,
+
+    // Scores to maximize for multi-objective selection
+    pub objectives: Vec<f32>,
+
+    pub lineage: Lineage,
 }
 
 impl PartialEq for Network {
@@ -169,11 +187,16 @@ impl Network {
         }
 
         Network {
+            id: 0,
             nodes,
             links: vec!(),
             input_count: inputs,
             output_count: outputs,
             fitness: 0.0,
+            behavior: Vec::new(),
+            novelty: 0.0,
+            objectives: Vec::new(),
+            lineage: Lineage::new(),
         }
     }
 
@@ -181,57 +204,97 @@ impl Network {
         (2.0 / (1.0 + (-x).exp())) - 1.0
     }
 
-    pub fn add_link(&mut self, global_inno_number: &mut u32, node_index_1: usize, node_index_2: usize, weight: f32) -> Result<(), &'static str> {
-        let node1 = self.nodes[node_index_1].clone();
-        let node2 = self.nodes[node_index_2].clone();
+    // Returns the (in, out) ordering a link between two nodes would have.
+    // Inputs always feed forward, outputs are always fed, and hidden nodes
+    // link from the lower level to the higher one.
+    pub fn link_direction(&self, node_index_1: usize, node_index_2: usize) -> Result<(usize, usize), NeatError> {
+        let node1 = self.nodes.get(node_index_1).ok_or(NeatError::InvalidNode(node_index_1))?;
+        let node2 = self.nodes.get(node_index_2).ok_or(NeatError::InvalidNode(node_index_2))?;
 
-        if node1.node_type == node2.node_type && node1.level == node2.level {
-            return Err("Node types were equal and z indexes were equal");
+        if node_index_1 == node_index_2 || (node1.node_type != NodeType::Hidden && node1.node_type == node2.node_type) {
+            return Err(NeatError::SameLevel(node_index_1, node_index_2));
         }
-        
-        let mut in_index: usize = 0;
-        let mut out_index: usize = 0;
 
-        match node1.node_type {
-            NodeType::Input => {
-                in_index = node_index_1;
-                out_index = node_index_2;
-            },
-            NodeType::Output => {
-                in_index = node_index_2;
-                out_index = node_index_1;
-            },
-            NodeType::Hidden => {
-                if node1.level > node2.level {
-                    in_index = node_index_2;
-                    out_index = node_index_1; 
-                } else if node2.level > node1.level {
-                    in_index = node_index_1;
-                    out_index = node_index_2; 
-                } else {
-                    return Err("Node levels are the same");
+        if node1.node_type == NodeType::Input || node2.node_type == NodeType::Output {
+            Ok((node_index_1, node_index_2))
+        } else if node1.node_type == NodeType::Output || node2.node_type == NodeType::Input {
+            Ok((node_index_2, node_index_1))
+        } else if node1.level > node2.level {
+            Ok((node_index_2, node_index_1))
+        } else {
+            // Hidden nodes on the same level have no path between them,
+            // so either direction keeps the network acyclic
+            Ok((node_index_1, node_index_2))
+        }
+    }
+
+    // Recomputes hidden node levels as the longest path from the inputs, so
+    // every node sits on a higher level than all of the nodes feeding it
+    pub fn update_levels(&mut self) {
+        for node in &mut self.nodes {
+            node.level = match node.node_type {
+                NodeType::Hidden => 1,
+                _ => 0,
+            };
+        }
+
+        let mut changed = true;
+        let mut passes = 0;
+        while changed && passes < self.nodes.len() {
+            changed = false;
+            passes += 1;
+
+            for link in &self.links {
+                let in_node = self.nodes[link.in_index];
+                let out_node = self.nodes[link.out_index];
+                if in_node.node_type == NodeType::Hidden && out_node.node_type == NodeType::Hidden && out_node.level <= in_node.level {
+                    self.nodes[link.out_index].level = in_node.level + 1;
+                    changed = true;
                 }
-            },
+            }
+        }
+    }
+
+    // Checks that every link feeds a node evaluated after its source
+    pub fn is_ordered(&self) -> bool {
+        self.links.iter().all(|link| {
+            let in_node = self.nodes[link.in_index];
+            let out_node = self.nodes[link.out_index];
+            match (in_node.node_type, out_node.node_type) {
+                (NodeType::Output, _) | (_, NodeType::Input) => false,
+                (NodeType::Hidden, NodeType::Hidden) => in_node.level < out_node.level,
+                _ => true,
+            }
+        })
+    }
+
+    pub fn find_link(&self, in_index: usize, out_index: usize) -> Option<usize> {
+        self.links.iter().position(|link| link.in_index == in_index && link.out_index == out_index)
+    }
+
+    // Appends an unconnected hidden node and returns its index
+    pub fn add_hidden_node(&mut self) -> usize {
+        self.nodes.push(Node::new(NodeType::Hidden));
+        self.nodes.len() - 1
+    }
+
+    pub fn add_link(&mut self, global_inno_number: &mut u32, node_index_1: usize, node_index_2: usize, weight: f32) -> Result<(), NeatError> {
+        let (in_index, out_index) = self.link_direction(node_index_1, node_index_2)?;
+
+        if self.find_link(in_index, out_index).is_some() {
+            return Err(NeatError::LinkExists(in_index, out_index));
         }
 
         self.links.push(Link::new(global_inno_number.clone(), in_index, out_index, weight));
         *global_inno_number += 1;
+        self.update_levels();
         Ok(())
     }
 
-    pub fn add_node(&mut self, global_inno_number: &mut u32, link_index: usize) -> Result<(), &'static str> {
-        let link = self.links[link_index].clone();
-        let in_node = self.nodes[link.in_index].clone();
-        let out_node = self.nodes[link.out_index].clone();
+    pub fn add_node(&mut self, global_inno_number: &mut u32, link_index: usize) -> Result<(), NeatError> {
+        let link = self.links.get(link_index).ok_or(NeatError::InvalidLink(link_index))?.clone();
 
-        let level = in_node.level + 1;
-        if out_node.level == level {
-            self.nodes[link.out_index].level += 1;
-        }
-
-        let mut new_node = Node::new(NodeType::Hidden);
-        new_node.level = level;
-        self.nodes.push(new_node);
+        self.nodes.push(Node::new(NodeType::Hidden));
         let new_index = self.nodes.len() - 1;
         
         self.links[link_index].enabled = false;
@@ -240,43 +303,53 @@ impl Network {
         self.links.push(Link::new(*global_inno_number + 1, new_index, link.out_index, link.weight));
 
         *global_inno_number += 2;
+        self.update_levels();
         Ok(())
     }
 
-    pub fn add_random_link(&mut self, global_inno_number: &mut u32) -> Result<(), &'static str> {
-        let node1_index = rand::thread_rng().gen_range(0, self.nodes.len());
-        let mut node2_selection: Vec<usize> = Vec::new();
+    // Adds a link between a random unconnected pair of nodes. If reenable is set,
+    // pairs joined only by a disabled link are candidates too and get re-enabled.
+    pub fn add_random_link(&mut self, global_inno_number: &mut u32, reenable: bool) -> Result<(), NeatError> {
+        let mut new_pairs: Vec<(usize, usize)> = Vec::new();
+        let mut disabled_links: Vec<usize> = Vec::new();
 
         for i in 0..self.nodes.len() {
-            let node1 = self.nodes[node1_index].clone();
-            let node2 = self.nodes[i].clone();
-            if node1_index != i {
-                if (node1.node_type != node2.node_type) || (node1.level != node2.level) {
-                    let mut link_exists = false;
-                    for link in &self.links {
-                        if (link.in_index == node1_index && link.out_index == i) || (link.in_index == i && link.out_index == node1_index) {
-                            link_exists = true;
+            for j in (i + 1)..self.nodes.len() {
+                let (in_index, out_index) = match self.link_direction(i, j) {
+                    Ok(direction) => direction,
+                    Err(_) => continue,
+                };
+
+                match self.find_link(in_index, out_index) {
+                    None => new_pairs.push((in_index, out_index)),
+                    Some(link_index) => {
+                        if reenable && !self.links[link_index].enabled {
+                            disabled_links.push(link_index);
                         }
-                    }
-                    if true {
-                        node2_selection.push(i);
-                    }
+                    },
                 }
-            }                        
+            }
         }
 
-        if node2_selection.len() == 0 {
-            return Err("No nodes available");
+        let candidate_count = new_pairs.len() + disabled_links.len();
+        if candidate_count == 0 {
+            return Err(NeatError::FullyConnected);
         }
 
-        let node2_index = node2_selection[rand::thread_rng().gen_range(0, node2_selection.len())];
+        let choice = rand::thread_rng().gen_range(0, candidate_count);
+        if choice >= new_pairs.len() {
+            self.links[disabled_links[choice - new_pairs.len()]].enabled = true;
+            return Ok(());
+        }
+
+        let (in_index, out_index) = new_pairs[choice];
         let r: f32 = rand::thread_rng().gen();
         let weight: f32 = (r * 10.0) - 5.0;
 
-        self.add_link(global_inno_number, node1_index, node2_index, weight)
+        self.add_link(global_inno_number, in_index, out_index, weight)
     }
 
-    pub fn add_random_node(&mut self, global_inno_number: &mut u32) -> Result<(), &'static str> {
+    pub fn add_random_node(&mut self, global_inno_number: &mut u32) -> Result<(), NeatError> {
         let mut available_links: Vec<usize> = Vec::new();
         for i in 0..self.links.len() {
             if self.links[i].enabled {
@@ -285,7 +358,7 @@ impl Network {
         }
 
         if available_links.len() == 0 {
-            return Err("No links available");
+            return Err(NeatError::NoLinksAvailable);
         }
 
         let link_index = available_links[rand::thread_rng().gen_range(0, available_links.len())];
@@ -293,17 +366,6 @@ impl Network {
         self.add_node(global_inno_number, link_index)
     }
 
-    pub fn filter_node_indexes(&self, node_type: NodeType, level: u32) -> Vec<usize> {
-        let mut filtered_nodes: Vec<usize> = Vec::new();
-        for i in 0..self.nodes.len() {
-            let node = self.nodes[i];
-            if node.node_type == node_type && node.level == level {
-                filtered_nodes.push(i);
-            }
-        }
-        filtered_nodes
-    }
-
     pub fn input_links(&self, node_index: usize) -> Vec<usize> {
         let mut inputs: Vec<usize> = Vec::new();
         for i in 0..self.links.len() {
@@ -327,15 +389,14 @@ impl Network {
         }
 
         // Compute hidden nodes
-        let mut current_level = 0u32;
-        let mut hidden_nodes_collection: Vec<usize> = vec!(0);
+        let hidden_start = (self.input_count + self.output_count) as usize;
+        let max_level = self.nodes[hidden_start..].iter().map(|node| node.level).max().unwrap_or(0);
 
-        while hidden_nodes_collection.len() != 0 {
-            current_level += 1;
-            hidden_nodes_collection = Vec::new();
+        for current_level in 1..=max_level {
+            let mut hidden_nodes_collection: Vec<usize> = Vec::new();
 
             // Collect hidden nodes with correct level
-            for i in ((self.input_count + self.output_count) as usize)..self.nodes.len() {
+            for i in hidden_start..self.nodes.len() {
                 let node = &self.nodes[i];
                 if node.level == current_level {
                     hidden_nodes_collection.push(i);
@@ -343,7 +404,7 @@ impl Network {
             }
 
             // Evaluate group of hidden nodes
-            for hidden_node_index in hidden_nodes_collection.clone() {
+            for hidden_node_index in hidden_nodes_collection {
                 let link_indexes = self.input_links(hidden_node_index);
 
                 for link_index in link_indexes {
@@ -376,78 +437,250 @@ impl Network {
         return node_values[(self.input_count as usize)..((self.input_count + self.output_count) as usize)].to_vec();
     }
 
-    pub fn draw(&self, canvas: &mut WindowCanvas, x: f32, y: f32, width: f32, height: f32) {
-        let mut node_layer_counts: Vec<u32> = Vec::new();
-        node_layer_counts.push(self.input_count);
-        node_layer_counts.push(self.output_count);
-
-        let mut hidden_level = 0;
-        let mut done_hidden = false;
-
-        // Set node_layer_counts
-        while !done_hidden {
-            hidden_level += 1;
-            let filtered_node_indexes = self.filter_node_indexes(NodeType::Hidden, hidden_level);
-            if filtered_node_indexes.len() == 0 {
-                done_hidden = true;
-            } else {
-                node_layer_counts.push(filtered_node_indexes.len() as u32);
-            }
+    // Serializes the genome as one line per field, node and link:
+    //   network <inputs> <outputs>
+    //   id <id>
+    //   fitness <fitness>
+    //   node <input|output|hidden> <level>
+    //   link <inno> <in> <out> <weight> <enabled 0|1>
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+        output += format!("network {} {}\n", self.input_count, self.output_count).as_str();
+        output += format!("id {}\n", self.id).as_str();
+        output += format!("fitness {}\n", self.fitness).as_str();
+
+        for node in &self.nodes {
+            let node_str = match node.node_type {
+                NodeType::Input => "input",
+                NodeType::Output => "output",
+                NodeType::Hidden => "hidden",
+            };
+            output += format!("node {} {}\n", node_str, node.level).as_str();
         }
 
+        for link in &self.links {
+            output += format!("link {} {} {} {} {}\n", link.inno_number, link.in_index, link.out_index, link.weight, link.enabled as u8).as_str();
+        }
+
+        output
+    }
+
+    pub fn from_text(text: &str) -> io::Result<Network> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid genome line: {}", line));
+
+        let mut network = Network::new(0, 0);
+        network.nodes = Vec::new();
 
-        // Find maximum value of node_layer_counts
-        let mut most_layer_nodes = 0;
-        for layer_nodes in node_layer_counts.clone() {
-            if layer_nodes > most_layer_nodes {
-                most_layer_nodes = layer_nodes;
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() == 0 {
+                continue;
+            }
+
+            let parse_error = |_| invalid(line);
+            match (fields[0], fields.len()) {
+                ("network", 3) => {
+                    network.input_count = fields[1].parse().map_err(parse_error)?;
+                    network.output_count = fields[2].parse().map_err(parse_error)?;
+                },
+                ("id", 2) => network.id = fields[1].parse().map_err(parse_error)?,
+                ("fitness", 2) => network.fitness = fields[1].parse().map_err(|_| invalid(line))?,
+                ("node", 3) => {
+                    let node_type = match fields[1] {
+                        "input" => NodeType::Input,
+                        "output" => NodeType::Output,
+                        "hidden" => NodeType::Hidden,
+                        _ => return Err(invalid(line)),
+                    };
+                    let mut node = Node::new(node_type);
+                    node.level = fields[2].parse().map_err(parse_error)?;
+                    network.nodes.push(node);
+                },
+                ("link", 6) => {
+                    let mut link = Link::new(
+                        fields[1].parse().map_err(parse_error)?,
+                        fields[2].parse().map_err(parse_error)?,
+                        fields[3].parse().map_err(parse_error)?,
+                        fields[4].parse().map_err(|_| invalid(line))?,
+                    );
+                    link.enabled = fields[5] == "1";
+                    network.links.push(link);
+                },
+                _ => return Err(invalid(line)),
             }
         }
 
-        // Set Layer Spacings
-        let x_spacing: f32 = width / node_layer_counts.len() as f32;
-        let y_spacing: f32 = height / most_layer_nodes as f32;
+        // Reject genomes that would index out of bounds in evaluate
+        let expected_io = (network.input_count + network.output_count) as usize;
+        let io_nodes_valid = network.nodes.len() >= expected_io
+            && network.nodes[..network.input_count as usize].iter().all(|n| n.node_type == NodeType::Input)
+            && network.nodes[network.input_count as usize..expected_io].iter().all(|n| n.node_type == NodeType::Output);
+        let links_valid = network.links.iter().all(|l| l.in_index < network.nodes.len() && l.out_index < network.nodes.len());
+        if !io_nodes_valid || !links_valid {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Genome nodes and links are inconsistent"));
+        }
+
+        // Levels in the file may be stale or hand-edited, so derive them from the links
+        network.update_levels();
+        if !network.is_ordered() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Genome links form a cycle or point into an input or out of an output"));
+        }
+
+        Ok(network)
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    pub fn load(path: &str) -> io::Result<Network> {
+        Network::from_text(&fs::read_to_string(path)?)
+    }
 
-        // Set Y Offsets to Vertically Center
-        let mut y_offsets: Vec<f32> = Vec::new();
-        for i in 0..node_layer_counts.len() {
-            let current_layer_count = node_layer_counts[i];
-            y_offsets.push(((most_layer_nodes - current_layer_count) as f32 / 2.0) * y_spacing);
-        }
-
-        // Set Node Positions
-        let mut node_positions: Vec<(f32, f32)> = Vec::new();
-        for i in 0..node_layer_counts.len() {
-            let layer_count = node_layer_counts[i];
-            let y_offset = y_offsets[i];
-            let x_index = match i {
-                0 => 0,
-                1 => node_layer_counts.len() - 1,
-                _ => i - 1,
+    const LEGEND_OFFSET: f32 = 60.0;
+    const LEGEND_WIDTH: f32 = 200.0;
+
+    // Largest absolute link weight, used to scale link colours
+    pub fn max_weight(&self) -> f32 {
+        self.links.iter().map(|l| l.weight.abs()).fold(std::f32::EPSILON, f32::max)
+    }
+
+    // Blue for positive weights and red for negative, brighter as |weight| approaches max_weight
+    pub fn weight_color(weight: f32, max_weight: f32) -> (u8, u8, u8, u8) {
+        let intensity = (weight.abs() / max_weight).min(1.0);
+        let channel = (60.0 + intensity * 195.0) as u8;
+        if weight > 0.0 {
+            (0, 0, channel, 255)
+        } else {
+            (channel, 0, 0, 255)
+        }
+    }
+
+    // Lays nodes out in columns: inputs first, then one column per hidden level,
+    // then outputs. The result is indexed by node index.
+    pub fn node_positions(&self, x: f32, y: f32, width: f32, height: f32) -> Vec<(f32, f32)> {
+        let max_level = self.nodes.iter()
+            .filter(|n| n.node_type == NodeType::Hidden)
+            .map(|n| n.level)
+            .max()
+            .unwrap_or(0);
+
+        // Build layers of node indexes
+        let mut layers: Vec<Vec<usize>> = vec![Vec::new(); max_level as usize + 2];
+        for i in 0..self.nodes.len() {
+            let node = &self.nodes[i];
+            let layer_index = match node.node_type {
+                NodeType::Input => 0,
+                NodeType::Hidden => node.level as usize,
+                NodeType::Output => max_level as usize + 1,
             };
-            for j in 0..layer_count {
-                node_positions.push((x + x_spacing * x_index as f32, y  + y_offset + y_spacing * j as f32));
+            layers[layer_index].push(i);
+        }
+
+        // Reduce crossings by ordering hidden layers by the barycenter of their
+        // neighbours, sweeping forward over inputs then backward over outputs
+        let mut ranks: Vec<f32> = vec![0.0; self.nodes.len()];
+        for layer in &layers {
+            for (rank, &node_index) in layer.iter().enumerate() {
+                ranks[node_index] = rank as f32;
             }
         }
 
-        // Draw Links
-        let line_thickness: u8 = 2;
-        for link in &self.links {
-            if link.enabled {
-                let color: (u8, u8, u8, u8) = if link.weight > 0.0 {
-                    (0, 0, (link.weight * 255.0) as u8, 255)
+        let hidden_layers: Vec<usize> = (1..layers.len() - 1).collect();
+        let sweeps = hidden_layers.iter().cloned().chain(hidden_layers.iter().rev().cloned());
+        for (sweep, layer_index) in sweeps.enumerate() {
+            let forward = sweep < hidden_layers.len();
+            let mut barycenters: Vec<(usize, f32)> = Vec::new();
+
+            for &node_index in &layers[layer_index] {
+                let neighbours: Vec<f32> = self.links.iter()
+                    .filter_map(|link| {
+                        if forward && link.out_index == node_index {
+                            Some(ranks[link.in_index])
+                        } else if !forward && link.in_index == node_index {
+                            Some(ranks[link.out_index])
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+
+                let barycenter = if neighbours.len() == 0 {
+                    ranks[node_index]
                 } else {
-                    ((-link.weight * 255.0) as u8, 0, 0, 255)
+                    neighbours.iter().sum::<f32>() / neighbours.len() as f32
                 };
+                barycenters.push((node_index, barycenter));
+            }
+
+            barycenters.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            layers[layer_index] = barycenters.iter().map(|b| b.0).collect();
+            for (rank, &node_index) in layers[layer_index].iter().enumerate() {
+                ranks[node_index] = rank as f32;
+            }
+        }
+
+        // Set Layer Spacings
+        let most_layer_nodes = layers.iter().map(|l| l.len()).max().unwrap_or(1).max(1);
+        let x_spacing: f32 = width / layers.len() as f32;
+        let y_spacing: f32 = height / most_layer_nodes as f32;
+
+        // Set Node Positions, vertically centering each layer
+        let mut node_positions: Vec<(f32, f32)> = vec![(x, y); self.nodes.len()];
+        for (layer_index, layer) in layers.iter().enumerate() {
+            let y_offset = ((most_layer_nodes - layer.len()) as f32 / 2.0) * y_spacing;
+            for (j, &node_index) in layer.iter().enumerate() {
+                node_positions[node_index] = (x + x_spacing * layer_index as f32, y + y_offset + y_spacing * j as f32);
+            }
+        }
 
-                let in_pos = node_positions[link.in_index];
-                let out_pos = node_positions[link.out_index];
-            
-                canvas.aa_line(in_pos.0 as i16, in_pos.1 as i16, out_pos.0 as i16, out_pos.1 as i16, color)
-                    .expect("Failed to draw link")
+        node_positions
+    }
+
+    pub fn draw(&self, canvas: &mut WindowCanvas, x: f32, y: f32, width: f32, height: f32) {
+        let node_positions = self.node_positions(x, y, width, height);
+
+        let max_weight = self.max_weight();
+
+        // Draw disabled links first as faded dashes so enabled ones stay on top
+        for link in self.links.iter().filter(|l| !l.enabled) {
+            let in_pos = node_positions[link.in_index];
+            let out_pos = node_positions[link.out_index];
+            let length = ((out_pos.0 - in_pos.0).powf(2.0) + (out_pos.1 - in_pos.1).powf(2.0)).sqrt();
+            let dash_count = (length / 8.0) as usize;
+
+            for i in (0..dash_count).step_by(2) {
+                let t1 = i as f32 / dash_count as f32;
+                let t2 = (i + 1) as f32 / dash_count as f32;
+                canvas.aa_line(
+                    (in_pos.0 + (out_pos.0 - in_pos.0) * t1) as i16, (in_pos.1 + (out_pos.1 - in_pos.1) * t1) as i16,
+                    (in_pos.0 + (out_pos.0 - in_pos.0) * t2) as i16, (in_pos.1 + (out_pos.1 - in_pos.1) * t2) as i16,
+                    (120, 120, 120, 90),
+                ).expect("Failed to draw disabled link");
             }
         }
 
+        // Draw enabled links coloured and thickened by their share of the largest weight
+        for link in self.links.iter().filter(|l| l.enabled) {
+            let color = Network::weight_color(link.weight, max_weight);
+            let thickness = 1 + (link.weight.abs() / max_weight * 5.0) as u8;
+
+            let in_pos = node_positions[link.in_index];
+            let out_pos = node_positions[link.out_index];
+
+            canvas.thick_line(in_pos.0 as i16, in_pos.1 as i16, out_pos.0 as i16, out_pos.1 as i16, thickness, color)
+                .expect("Failed to draw link");
+        }
+
+        // Draw legend from -max_weight to +max_weight above the network
+        let legend_steps = 50;
+        for i in 0..legend_steps {
+            let weight = (i as f32 / (legend_steps - 1) as f32 * 2.0 - 1.0) * max_weight;
+            let legend_x = x + Network::LEGEND_OFFSET + i as f32 * Network::LEGEND_WIDTH / legend_steps as f32;
+            canvas.box_(legend_x as i16, (y - 45.0) as i16, (legend_x + Network::LEGEND_WIDTH / legend_steps as f32) as i16, (y - 37.0) as i16, Network::weight_color(weight, max_weight))
+                .expect("Failed to draw legend");
+        }
+
         // Draw Nodes
         let circle_radius: i16 = 10;
         for i in 0..self.nodes.len() {
@@ -463,4 +696,224 @@ impl Network {
                 .expect("Failed to draw node");
         }
     }
-}
\ No newline at end of file
+
+    // Labels each node with its index and type, and optionally each enabled link with its weight
+    pub fn draw_labels(&self, canvas: &mut WindowCanvas, text: &TextRenderer, x: f32, y: f32, width: f32, height: f32, show_weights: bool) {
+        let node_positions = self.node_positions(x, y, width, height);
+        let label_color = Color::RGB(220, 220, 220);
+
+        // Legend range
+        let max_weight = self.max_weight();
+        text.draw(canvas, format!("{:.2}", -max_weight).as_str(), x as i32, (y - 50.0) as i32, label_color);
+        text.draw(canvas, format!("{:.2}", max_weight).as_str(), (x + Network::LEGEND_OFFSET + Network::LEGEND_WIDTH + 10.0) as i32, (y - 50.0) as i32, label_color);
+
+        if show_weights {
+            for link in &self.links {
+                if link.enabled {
+                    let in_pos = node_positions[link.in_index];
+                    let out_pos = node_positions[link.out_index];
+                    let mid = ((in_pos.0 + out_pos.0) / 2.0, (in_pos.1 + out_pos.1) / 2.0);
+                    text.draw(canvas, format!("{:.2}", link.weight).as_str(), mid.0 as i32, mid.1 as i32, Color::RGB(180, 180, 180));
+                }
+            }
+        }
+
+        for i in 0..self.nodes.len() {
+            let pos = node_positions[i];
+            let type_str = match self.nodes[i].node_type {
+                NodeType::Input => "I",
+                NodeType::Output => "O",
+                NodeType::Hidden => "H",
+            };
+            text.draw(canvas, format!("{} {}", i, type_str).as_str(), pos.0 as i32 + 12, pos.1 as i32 - 24, label_color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splitting_a_chain_pushes_downstream_levels() {
+        let mut inno = 1;
+        let mut n = Network::new(2, 1);
+        n.add_link(&mut inno, 0, 2, 1.0).unwrap();
+        n.add_node(&mut inno, 0).unwrap();
+        n.add_node(&mut inno, 2).unwrap();
+
+        // Splitting 3 -> 4 gives 0 -> 3 -> 5 -> 4 -> 2, so node 4 moves up to level 3
+        n.add_node(&mut inno, 3).unwrap();
+
+        assert!(n.is_ordered());
+        assert_eq!(n.nodes[3].level, 1);
+        assert_eq!(n.nodes[5].level, 2);
+        assert_eq!(n.nodes[4].level, 3);
+    }
+
+    #[test]
+    fn hidden_to_output_links_feed_the_output() {
+        let mut inno = 1;
+        let mut n = Network::new(1, 1);
+        n.add_link(&mut inno, 0, 1, 1.0).unwrap();
+        n.add_node(&mut inno, 0).unwrap();
+        n.add_node(&mut inno, 2).unwrap();
+
+        let (in_index, out_index) = n.link_direction(3, 1).unwrap();
+        assert_eq!((in_index, out_index), (3, 1));
+    }
+
+    #[test]
+    fn random_links_never_repeat_a_pair() {
+        let mut inno = 1;
+        let mut n = Network::new(3, 2);
+        n.add_link(&mut inno, 0, 3, 1.0).unwrap();
+        n.add_node(&mut inno, 0).unwrap();
+
+        while n.add_random_link(&mut inno, false).is_ok() {}
+
+        let mut pairs: Vec<(usize, usize)> = n.links.iter().map(|l| (l.in_index, l.out_index)).collect();
+        let link_count = pairs.len();
+        pairs.sort();
+        pairs.dedup();
+        assert_eq!(pairs.len(), link_count);
+    }
+
+    #[test]
+    fn fully_connected_network_has_no_random_link() {
+        let mut inno = 1;
+        let mut n = Network::new(2, 1);
+        n.add_link(&mut inno, 0, 2, 1.0).unwrap();
+        n.add_link(&mut inno, 1, 2, 1.0).unwrap();
+
+        assert_eq!(n.add_random_link(&mut inno, true), Err(NeatError::FullyConnected));
+        assert_eq!(n.links.len(), 2);
+        assert_eq!(inno, 3);
+    }
+
+    #[test]
+    fn reenabling_keeps_the_innovation_number() {
+        let mut inno = 1;
+        let mut n = Network::new(1, 1);
+        n.add_link(&mut inno, 0, 1, 1.0).unwrap();
+        n.links[0].enabled = false;
+
+        // Without re-enabling the disabled link still occupies the only pair
+        assert_eq!(n.add_random_link(&mut inno, false), Err(NeatError::FullyConnected));
+
+        n.add_random_link(&mut inno, true).unwrap();
+        assert_eq!(n.links.len(), 1);
+        assert!(n.links[0].enabled);
+        assert_eq!(n.links[0].inno_number, 1);
+        assert_eq!(inno, 2);
+    }
+
+    #[test]
+    fn adding_an_existing_link_fails() {
+        let mut inno = 1;
+        let mut n = Network::new(1, 1);
+        n.add_link(&mut inno, 0, 1, 1.0).unwrap();
+
+        // Either node order names the same link
+        assert_eq!(n.add_link(&mut inno, 0, 1, 0.5), Err(NeatError::LinkExists(0, 1)));
+        assert_eq!(n.add_link(&mut inno, 1, 0, 0.5), Err(NeatError::LinkExists(0, 1)));
+        assert_eq!(n.links.len(), 1);
+        assert_eq!(inno, 2);
+    }
+
+    #[test]
+    fn random_mutations_stay_ordered() {
+        for _ in 0..200 {
+            let mut inno = 1;
+            let mut n = Network::new(3, 2);
+
+            for _ in 0..40 {
+                if rand::thread_rng().gen::<bool>() {
+                    let _ = n.add_random_node(&mut inno);
+                } else {
+                    let _ = n.add_random_link(&mut inno, true);
+                }
+                assert!(n.is_ordered(), "{}", n);
+            }
+
+            let inputs = vec!(0.5, -1.0, 1.0);
+            let mut values = vec![None; n.nodes.len()];
+            let expected: Vec<f32> = (3..5).map(|output| evaluate_recursively(&n, &inputs, output, &mut values)).collect();
+            assert_eq!(n.evaluate(inputs), expected, "{}", n);
+        }
+    }
+
+    // Value of a node found by recursing through its enabled input links, so it
+    // does not depend on the node levels Network::evaluate orders nodes by
+    fn evaluate_recursively(n: &Network, inputs: &[f32], node: usize, values: &mut Vec<Option<f32>>) -> f32 {
+        if node < n.input_count as usize {
+            return inputs[node];
+        }
+        if let Some(value) = values[node] {
+            return value;
+        }
+
+        let mut sum = 0.0;
+        for link in n.links.iter().filter(|l| l.enabled && l.out_index == node) {
+            sum += evaluate_recursively(n, inputs, link.in_index, values) * link.weight;
+        }
+
+        let value = Network::sigmoid(sum);
+        values[node] = Some(value);
+        value
+    }
+
+    #[test]
+    fn text_round_trip_preserves_genome() {
+        let mut inno = 1;
+        let mut n = Network::new(2, 1);
+        n.add_link(&mut inno, 0, 2, -0.37).unwrap();
+        n.add_link(&mut inno, 1, 2, 2.5).unwrap();
+        n.add_node(&mut inno, 0).unwrap();
+        n.id = 7;
+        n.fitness = 0.25;
+
+        let loaded = Network::from_text(&n.to_text()).unwrap();
+        assert_eq!(loaded.to_text(), n.to_text());
+        assert_eq!(loaded.evaluate(vec!(1.0, 0.5)), n.evaluate(vec!(1.0, 0.5)));
+        assert!(Network::from_text("link 1 0 9 1.0 1").is_err());
+    }
+
+    #[test]
+    fn loading_recomputes_levels_and_rejects_bad_links() {
+        // Hidden node 3 feeds hidden node 2 but the file puts both on level 1
+        let stale = "network 1 1\nnode input 0\nnode output 0\nnode hidden 1\nnode hidden 1\n\
+            link 1 0 3 1.0 1\nlink 2 3 2 1.0 1\nlink 3 2 1 1.0 1\n";
+        let loaded = Network::from_text(stale).unwrap();
+        assert!(loaded.is_ordered());
+        assert_eq!((loaded.nodes[3].level, loaded.nodes[2].level), (1, 2));
+
+        let cycle = "network 1 1\nnode input 0\nnode output 0\nnode hidden 1\nnode hidden 2\n\
+            link 1 2 3 1.0 1\nlink 2 3 2 1.0 1\n";
+        assert!(Network::from_text(cycle).is_err());
+
+        let into_input = "network 1 1\nnode input 0\nnode output 0\nnode hidden 1\nlink 1 2 0 1.0 1\n";
+        assert!(Network::from_text(into_input).is_err());
+
+        let out_of_output = "network 1 1\nnode input 0\nnode output 0\nnode hidden 1\nlink 1 1 2 1.0 1\n";
+        assert!(Network::from_text(out_of_output).is_err());
+    }
+
+    #[test]
+    fn node_positions_follow_node_indexes() {
+        let mut inno = 1;
+        let mut n = Network::new(2, 1);
+        n.add_link(&mut inno, 0, 2, 1.0).unwrap();
+        n.add_node(&mut inno, 0).unwrap();
+        n.add_node(&mut inno, 2).unwrap();
+
+        let positions = n.node_positions(0.0, 0.0, 400.0, 100.0);
+
+        // Inputs, level 1, level 2, then outputs in the last column
+        assert_eq!(positions[0].0, 0.0);
+        assert_eq!(positions[1].0, 0.0);
+        assert_eq!(positions[3].0, 100.0);
+        assert_eq!(positions[4].0, 200.0);
+        assert_eq!(positions[2].0, 300.0);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::neat::compare_scores;
+
+// Behaviours novel enough to be remembered across generations
+#[derive(Debug, Clone)]
+pub struct NoveltyArchive {
+    pub behaviors: Vec<Vec<f32>>,
+    // Novelty a behaviour needs to be added to the archive
+    pub threshold: f32,
+    // Number of nearest neighbours averaged for novelty
+    pub k: usize,
+}
+
+impl NoveltyArchive {
+    pub fn new() -> Self {
+        NoveltyArchive {
+            behaviors: Vec::new(),
+            threshold: 1.0,
+            k: 15,
+        }
+    }
+
+    // Mean distance from the behaviour to its k nearest neighbours among the
+    // population (excluding itself) and the archive
+    pub fn novelty(&self, index: usize, population: &[Vec<f32>]) -> f32 {
+        let behavior = &population[index];
+        let mut distances: Vec<f32> = population.iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, other)| behavior_distance(behavior, other))
+            .chain(self.behaviors.iter().map(|other| behavior_distance(behavior, other)))
+            .collect();
+
+        if distances.len() == 0 {
+            return 0.0;
+        }
+
+        distances.sort_by(|a, b| compare_scores(*a, *b));
+        let neighbours = self.k.min(distances.len()).max(1);
+        distances[..neighbours].iter().sum::<f32>() / neighbours as f32
+    }
+
+    // Scores the population, archives novel behaviours and adapts the threshold
+    // so roughly a few behaviours are added each generation
+    pub fn update(&mut self, population: &[Vec<f32>]) -> Vec<f32> {
+        let scores: Vec<f32> = (0..population.len()).map(|i| self.novelty(i, population)).collect();
+
+        let mut added = 0;
+        for i in 0..population.len() {
+            if scores[i] > self.threshold {
+                self.behaviors.push(population[i].clone());
+                added += 1;
+            }
+        }
+
+        if added > 4 {
+            self.threshold *= 1.2;
+        } else if added == 0 {
+            self.threshold *= 0.95;
+        }
+
+        scores
+    }
+}
+
+pub fn behavior_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powf(2.0)).sum::<f32>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn novelty_averages_the_nearest_neighbours() {
+        let population = vec!(vec!(0.0), vec!(1.0), vec!(3.0));
+        let mut archive = NoveltyArchive::new();
+
+        archive.k = 1;
+        assert_eq!(archive.novelty(0, &population), 1.0);
+        assert_eq!(archive.novelty(2, &population), 2.0);
+
+        archive.k = 2;
+        assert_eq!(archive.novelty(0, &population), 2.0);
+
+        // Archived behaviours count as neighbours too
+        archive.k = 1;
+        archive.behaviors.push(vec!(0.5));
+        assert_eq!(archive.novelty(0, &population), 0.5);
+    }
+
+    #[test]
+    fn update_archives_novel_behaviours_and_adapts_threshold() {
+        let population = vec!(vec!(0.0, 0.0), vec!(1.0, 0.0), vec!(5.0, 0.0));
+        let mut archive = NoveltyArchive::new();
+        archive.k = 1;
+        archive.threshold = 1.5;
+
+        assert_eq!(archive.update(&population), vec!(1.0, 1.0, 4.0));
+        assert_eq!(archive.behaviors, vec!(vec!(5.0, 0.0)));
+        assert_eq!(archive.threshold, 1.5);
+
+        // Nothing is novel the second time, so the threshold drops
+        assert_eq!(archive.update(&population), vec!(1.0, 1.0, 0.0));
+        assert_eq!(archive.behaviors.len(), 1);
+        assert!(archive.threshold < 1.5);
+    }
+}
@@ -0,0 +1,147 @@
+// NSGA-II style ranking for multi-objective selection. Every objective is maximized.
+
+use crate::neat::compare_scores;
+use crate::network::*;
+
+// The usual trade-off: fitness to maximize and enabled links to minimize
+pub fn fitness_and_size(fitness: f32, n: &Network) -> Vec<f32> {
+    vec!(fitness, -(n.links.iter().filter(|l| l.enabled).count() as f32))
+}
+
+// True if a is at least as good as b on every objective and better on at least one
+pub fn dominates(a: &[f32], b: &[f32]) -> bool {
+    let mut better = false;
+    for (x, y) in a.iter().zip(b) {
+        if x < y {
+            return false;
+        }
+        if x > y {
+            better = true;
+        }
+    }
+    better
+}
+
+// Splits indexes into successive non-dominated fronts, best front first
+pub fn non_dominated_fronts(objectives: &[Vec<f32>]) -> Vec<Vec<usize>> {
+    let count = objectives.len();
+    let mut dominated_by: Vec<Vec<usize>> = vec![Vec::new(); count];
+    let mut domination_counts: Vec<usize> = vec![0; count];
+
+    for i in 0..count {
+        for j in 0..count {
+            if dominates(&objectives[i], &objectives[j]) {
+                dominated_by[i].push(j);
+            } else if dominates(&objectives[j], &objectives[i]) {
+                domination_counts[i] += 1;
+            }
+        }
+    }
+
+    let mut fronts: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = (0..count).filter(|&i| domination_counts[i] == 0).collect();
+
+    while current.len() != 0 {
+        let mut next: Vec<usize> = Vec::new();
+        for &i in &current {
+            for &j in &dominated_by[i] {
+                domination_counts[j] -= 1;
+                if domination_counts[j] == 0 {
+                    next.push(j);
+                }
+            }
+        }
+
+        fronts.push(current);
+        current = next;
+    }
+
+    fronts
+}
+
+// Crowding distance of each member of a front, in the same order as the front.
+// Boundary points get infinite distance so the extremes are always kept.
+pub fn crowding_distances(objectives: &[Vec<f32>], front: &[usize]) -> Vec<f32> {
+    let mut distances: Vec<f32> = vec![0.0; front.len()];
+    if front.len() == 0 {
+        return distances;
+    }
+
+    for objective in 0..objectives[front[0]].len() {
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|&a, &b| compare_scores(objectives[front[a]][objective], objectives[front[b]][objective]));
+
+        let min = objectives[front[order[0]]][objective];
+        let max = objectives[front[order[order.len() - 1]]][objective];
+
+        distances[order[0]] = std::f32::INFINITY;
+        distances[order[order.len() - 1]] = std::f32::INFINITY;
+
+        if max - min < std::f32::EPSILON {
+            continue;
+        }
+
+        for i in 1..order.len().saturating_sub(1) {
+            let gap = objectives[front[order[i + 1]]][objective] - objectives[front[order[i - 1]]][objective];
+            distances[order[i]] += gap / (max - min);
+        }
+    }
+
+    distances
+}
+
+// Indexes ordered for selection: by front, then by descending crowding distance
+pub fn pareto_order(objectives: &[Vec<f32>]) -> Vec<usize> {
+    let mut order: Vec<usize> = Vec::new();
+
+    for front in non_dominated_fronts(objectives) {
+        let distances = crowding_distances(objectives, &front);
+        let mut ranked: Vec<(usize, f32)> = front.into_iter().zip(distances).collect();
+        ranked.sort_by(|a, b| compare_scores(b.1, a.1));
+        order.extend(ranked.iter().map(|r| r.0));
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fronts_follow_domination() {
+        let objectives = vec!(
+            vec!(1.0, 1.0),
+            vec!(3.0, 1.0),
+            vec!(1.0, 3.0),
+            vec!(2.0, 2.0),
+            vec!(0.5, 0.5),
+        );
+
+        let fronts = non_dominated_fronts(&objectives);
+        assert_eq!(fronts, vec!(vec!(1, 2, 3), vec!(0), vec!(4)));
+    }
+
+    #[test]
+    fn extremes_come_first_within_a_front() {
+        let objectives = vec!(
+            vec!(2.0, 2.0),
+            vec!(3.0, 1.0),
+            vec!(1.0, 3.0),
+        );
+
+        let order = pareto_order(&objectives);
+        assert_eq!(order[2], 0);
+    }
+
+    #[test]
+    fn nan_objectives_do_not_abort_ranking() {
+        let objectives = vec!(
+            vec!(1.0, std::f32::NAN),
+            vec!(2.0, 1.0),
+            vec!(std::f32::INFINITY, 0.0),
+        );
+
+        assert_eq!(pareto_order(&objectives).len(), 3);
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::fmt::{Formatter, Display};
+
+use crate::network::*;
+
+// What pruning removed, by innovation number for links and by index in the
+// original network for nodes
+#[derive(Debug, Clone, PartialEq)]
+pub struct PruneReport {
+    pub disabled_links: Vec<u32>,
+    // Hidden nodes no input reaches, which always output 0
+    pub unfed_nodes: Vec<usize>,
+    // Hidden nodes with no path to an output
+    pub dead_end_nodes: Vec<usize>,
+    // Enabled links to or from a removed node
+    pub orphaned_links: Vec<u32>,
+}
+
+impl PruneReport {
+    pub fn removed_nodes(&self) -> usize {
+        self.unfed_nodes.len() + self.dead_end_nodes.len()
+    }
+
+    pub fn removed_links(&self) -> usize {
+        self.disabled_links.len() + self.orphaned_links.len()
+    }
+}
+
+impl Display for PruneReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Removed {} nodes ({} without inputs, {} without a path to an output) and {} links ({} disabled, {} attached to removed nodes)",
+            self.removed_nodes(), self.unfed_nodes.len(), self.dead_end_nodes.len(),
+            self.removed_links(), self.disabled_links.len(), self.orphaned_links.len())
+    }
+}
+
+impl Network {
+    // Smallest network computing the same outputs. Unfed hidden nodes always
+    // output sigmoid(0) = 0, so they and everything they alone feed can go.
+    pub fn pruned(&self) -> (Network, PruneReport) {
+        let io_count = (self.input_count + self.output_count) as usize;
+        let enabled: Vec<&Link> = self.links.iter().filter(|l| l.enabled).collect();
+
+        // Nodes some input reaches through enabled links
+        let mut fed: Vec<bool> = (0..self.nodes.len()).map(|i| i < self.input_count as usize).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for link in &enabled {
+                if fed[link.in_index] && !fed[link.out_index] {
+                    fed[link.out_index] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        // Nodes with an enabled path to an output
+        let mut useful: Vec<bool> = (0..self.nodes.len()).map(|i| i >= self.input_count as usize && i < io_count).collect();
+        changed = true;
+        while changed {
+            changed = false;
+            for link in &enabled {
+                if useful[link.out_index] && !useful[link.in_index] {
+                    useful[link.in_index] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut report = PruneReport {
+            disabled_links: self.links.iter().filter(|l| !l.enabled).map(|l| l.inno_number).collect(),
+            unfed_nodes: Vec::new(),
+            dead_end_nodes: Vec::new(),
+            orphaned_links: Vec::new(),
+        };
+
+        // Old node index to new, for the nodes that stay
+        let mut new_indexes: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut pruned = self.clone();
+        pruned.nodes = Vec::new();
+        pruned.links = Vec::new();
+
+        for i in 0..self.nodes.len() {
+            if i >= io_count && !fed[i] {
+                report.unfed_nodes.push(i);
+            } else if i >= io_count && !useful[i] {
+                report.dead_end_nodes.push(i);
+            } else {
+                new_indexes[i] = Some(pruned.nodes.len());
+                pruned.nodes.push(self.nodes[i]);
+            }
+        }
+
+        // Links keep their order so every node sums its inputs the same way
+        for link in enabled {
+            match (new_indexes[link.in_index], new_indexes[link.out_index]) {
+                (Some(in_index), Some(out_index)) => {
+                    let mut kept = *link;
+                    kept.in_index = in_index;
+                    kept.out_index = out_index;
+                    pruned.links.push(kept);
+                },
+                _ => report.orphaned_links.push(link.inno_number),
+            }
+        }
+
+        pruned.update_levels();
+        (pruned, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neat::random;
+
+    #[test]
+    fn removes_dead_structure() {
+        // 0, 1 inputs, 2 output, 3 feeds the output, 4 is a dead end, 5 has no inputs
+        let mut n = Network::new(2, 1);
+        let mut inno = 0;
+        n.add_link(&mut inno, 0, 2, 1.0).unwrap();
+        n.add_node(&mut inno, 0).unwrap();
+        let dead_end = n.add_hidden_node();
+        n.add_link(&mut inno, 1, dead_end, 0.5).unwrap();
+        let unfed = n.add_hidden_node();
+        n.add_link(&mut inno, unfed, 2, -1.0).unwrap();
+
+        let (pruned, report) = n.pruned();
+        assert_eq!(pruned.nodes.len(), 4);
+        assert_eq!(report.disabled_links, vec!(0));
+        assert_eq!(report.dead_end_nodes, vec!(4));
+        assert_eq!(report.unfed_nodes, vec!(5));
+        assert_eq!(report.orphaned_links.len(), 2);
+        assert_eq!(pruned.links.iter().filter(|l| !l.enabled).count(), 0);
+        assert!(pruned.is_ordered());
+    }
+
+    #[test]
+    fn pruned_networks_compute_the_same_outputs() {
+        for _ in 0..50 {
+            let mut n = Network::new(4, 2);
+            let mut inno = 0;
+            for _ in 0..30 {
+                if random() < 0.3 {
+                    let _ = n.add_random_node(&mut inno);
+                } else {
+                    let _ = n.add_random_link(&mut inno, false);
+                }
+                if n.links.len() != 0 && random() < 0.2 {
+                    let index = (random() * n.links.len() as f32) as usize % n.links.len();
+                    n.links[index].enabled = false;
+                }
+            }
+
+            let (pruned, report) = n.pruned();
+            assert_eq!(pruned.nodes.len() + report.removed_nodes(), n.nodes.len());
+            assert_eq!(pruned.links.len() + report.removed_links(), n.links.len());
+
+            for _ in 0..10 {
+                let inputs: Vec<f32> = (0..4).map(|_| random() * 4.0 - 2.0).collect();
+                assert_eq!(pruned.evaluate(inputs.clone()), n.evaluate(inputs));
+            }
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::time::{Duration, Instant};
+
+use crate::distributed::*;
+use crate::error::NeatError;
+use crate::islands::*;
+use crate::neat::*;
+use crate::network::*;
+
+// Conditions that end a run. A run stops as soon as any set condition is met.
+#[derive(Debug, Clone, Copy)]
+pub struct Termination {
+    pub fitness_threshold: Option<f32>,
+    pub max_generations: Option<u32>,
+    pub time_budget: Option<Duration>,
+    // Generations without the best fitness improving
+    pub stagnation_limit: Option<u32>,
+}
+
+impl Termination {
+    pub fn new() -> Self {
+        Termination {
+            fitness_threshold: None,
+            max_generations: None,
+            time_budget: None,
+            stagnation_limit: None,
+        }
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        self.fitness_threshold.is_some() || self.max_generations.is_some() || self.time_budget.is_some() || self.stagnation_limit.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    FitnessReached,
+    MaxGenerations,
+    TimeBudget,
+    Stagnation,
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let reason_str = match self {
+            StopReason::FitnessReached => "fitness threshold reached",
+            StopReason::MaxGenerations => "maximum generations reached",
+            StopReason::TimeBudget => "time budget used up",
+            StopReason::Stagnation => "best fitness stopped improving",
+        };
+
+        write!(f, "{}", reason_str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    pub reason: StopReason,
+    pub generations: u32,
+    pub elapsed: Duration,
+    // Fittest network seen during the run
+    pub champion: Network,
+}
+
+impl Display for RunResult {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Stopped after {} generations in {:.2}s: {}. Champion {} has fitness {}",
+            self.generations, self.elapsed.as_secs_f64(), self.reason, self.champion.id, self.champion.fitness)
+    }
+}
+
+impl NEAT {
+    // Trains until a termination condition is met
+    pub fn run<F: FnMut(&Network) -> f32>(&mut self, termination: &Termination, mut fitness: F) -> Result<RunResult, NeatError> {
+        self.run_generations(termination, |neat| neat.train_with(&mut fitness))
+    }
+
+    // Like run, where the evaluation also reports a behaviour descriptor for
+    // novelty search
+    pub fn run_with_behavior<F: FnMut(&Network) -> (f32, Vec<f32>)>(&mut self, termination: &Termination, mut evaluate: F) -> Result<RunResult, NeatError> {
+        self.run_generations(termination, |neat| neat.train_with_behavior(&mut evaluate))
+    }
+
+    // Like run, where the evaluation reports several objectives for Pareto selection
+    pub fn run_with_objectives<F: FnMut(&Network) -> Vec<f32>>(&mut self, termination: &Termination, mut evaluate: F) -> Result<RunResult, NeatError> {
+        self.run_generations(termination, |neat| neat.train_with_objectives(&mut evaluate))
+    }
+
+    // Like run, with every network scored by the worker pool
+    pub fn run_distributed(&mut self, termination: &Termination, pool: &mut WorkerPool) -> Result<RunResult, NeatError> {
+        self.run_generations(termination, |neat| neat.train_distributed(pool))
+    }
+
+    fn run_generations<G: FnMut(&mut NEAT)>(&mut self, termination: &Termination, mut train: G) -> Result<RunResult, NeatError> {
+        run_until(termination, || {
+            train(self);
+            self.champion.clone().expect("Population is empty")
+        })
+    }
+}
+
+impl Islands {
+    // Trains every island until a termination condition is met, judging the
+    // conditions on the best champion of all islands
+    pub fn run<F: FnMut(&Network) -> f32>(&mut self, termination: &Termination, mut fitness: F) -> Result<RunResult, NeatError> {
+        run_until(termination, || {
+            self.train_with(&mut fitness);
+            self.islands.iter()
+                .filter_map(|neat| neat.champion.clone())
+                .min_by(|a, b| compare_scores(b.fitness, a.fitness))
+                .expect("No islands to train")
+        })
+    }
+}
+
+// Calls step, which trains one generation and returns its champion, until a
+// termination condition is met
+fn run_until<G: FnMut() -> Network>(termination: &Termination, mut step: G) -> Result<RunResult, NeatError> {
+    if !termination.is_bounded() {
+        return Err(NeatError::Unbounded);
+    }
+
+    let start = Instant::now();
+    let mut generations = 0;
+    let mut best: Option<Network> = None;
+    let mut stagnant_generations = 0;
+
+    loop {
+        let champion = step();
+        generations += 1;
+
+        if best.as_ref().map_or(true, |b| champion.fitness > b.fitness) {
+            best = Some(champion);
+            stagnant_generations = 0;
+        } else {
+            stagnant_generations += 1;
+        }
+        let best_fitness = best.as_ref().unwrap().fitness;
+
+        let reason = if termination.fitness_threshold.map_or(false, |t| best_fitness >= t) {
+            Some(StopReason::FitnessReached)
+        } else if termination.max_generations.map_or(false, |m| generations >= m) {
+            Some(StopReason::MaxGenerations)
+        } else if termination.time_budget.map_or(false, |t| start.elapsed() >= t) {
+            Some(StopReason::TimeBudget)
+        } else if termination.stagnation_limit.map_or(false, |s| stagnant_generations >= s) {
+            Some(StopReason::Stagnation)
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            return Ok(RunResult {
+                reason,
+                generations,
+                elapsed: start.elapsed(),
+                champion: best.unwrap(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pareto::*;
+
+    #[test]
+    fn stops_when_fitness_threshold_is_reached() {
+        // Every evaluation scores one more than the last, so generation g peaks at 30 * g
+        let mut neat = NEAT::new(30, 3, 1);
+        let mut evaluations = 0;
+        let mut termination = Termination::new();
+        termination.fitness_threshold = Some(70.0);
+        termination.max_generations = Some(10);
+
+        let result = neat.run(&termination, |_| {
+            evaluations += 1;
+            evaluations as f32
+        }).unwrap();
+        assert_eq!(result.reason, StopReason::FitnessReached);
+        assert_eq!(result.generations, 3);
+        assert_eq!(result.champion.fitness, 90.0);
+    }
+
+    #[test]
+    fn stops_after_max_generations() {
+        let mut neat = NEAT::new(30, 3, 1);
+        let mut termination = Termination::new();
+        termination.fitness_threshold = Some(1.0);
+        termination.max_generations = Some(4);
+
+        let result = neat.run(&termination, |_| 0.5).unwrap();
+        assert_eq!(result.reason, StopReason::MaxGenerations);
+        assert_eq!(result.generations, 4);
+    }
+
+    #[test]
+    fn stops_when_best_fitness_stagnates() {
+        // The first generation sets the best fitness, the next two fail to beat it
+        let mut neat = NEAT::new(30, 3, 1);
+        let mut termination = Termination::new();
+        termination.stagnation_limit = Some(2);
+        termination.max_generations = Some(10);
+
+        let result = neat.run(&termination, |_| 0.5).unwrap();
+        assert_eq!(result.reason, StopReason::Stagnation);
+        assert_eq!(result.generations, 3);
+    }
+
+    #[test]
+    fn stops_when_time_budget_is_used_up() {
+        let mut neat = NEAT::new(30, 3, 1);
+        let mut termination = Termination::new();
+        termination.time_budget = Some(Duration::from_secs(0));
+
+        let result = neat.run(&termination, |_| 0.5).unwrap();
+        assert_eq!(result.reason, StopReason::TimeBudget);
+        assert_eq!(result.generations, 1);
+    }
+
+    #[test]
+    fn refuses_unbounded_runs() {
+        let mut neat = NEAT::new(30, 3, 1);
+        let mut evaluations = 0;
+
+        let result = neat.run(&Termination::new(), |_| {
+            evaluations += 1;
+            0.5
+        });
+        assert_eq!(result.err(), Some(NeatError::Unbounded));
+        assert_eq!(evaluations, 0);
+    }
+
+    #[test]
+    fn pareto_runs_keep_the_evaluated_front() {
+        let mut neat = NEAT::new(30, 3, 1);
+        neat.selection = SelectionMode::Pareto;
+        neat.change_weights_chance = 1.0;
+        let mut termination = Termination::new();
+        termination.max_generations = Some(3);
+
+        let objectives = |n: &Network| vec!((n.id % 7) as f32, -((n.id % 5) as f32));
+        neat.run_with_objectives(&termination, objectives).unwrap();
+
+        assert!(neat.pareto_front.len() != 0);
+        for n in &neat.pareto_front {
+            assert_eq!(n.objectives, objectives(n));
+            assert!(neat.pareto_front.iter().all(|other| !dominates(&other.objectives, &n.objectives)));
+        }
+    }
+
+    #[test]
+    fn islands_stop_on_the_best_champion_of_any_island() {
+        let mut islands = Islands::new(vec!(NEAT::new(30, 3, 1), NEAT::new(30, 3, 1)), Topology::Ring);
+        let mut evaluations = 0;
+        let mut termination = Termination::new();
+        termination.fitness_threshold = Some(100.0);
+        termination.max_generations = Some(10);
+
+        // The second island's networks are scored after the first's, so it holds every champion
+        let result = islands.run(&termination, |_| {
+            evaluations += 1;
+            evaluations as f32
+        }).unwrap();
+        assert_eq!(result.reason, StopReason::FitnessReached);
+        assert_eq!(result.generations, 2);
+        assert_eq!(result.champion.fitness, 120.0);
+        assert_eq!(islands.generation, 2);
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::neat::compare_scores;
+use crate::network::*;
+
+#[derive(Debug, Clone)]
+pub struct GenerationStats {
+    pub generation: u32,
+
+    // Fitness
+    pub best_fitness: f32,
+    pub mean_fitness: f32,
+    pub median_fitness: f32,
+    pub std_fitness: f32,
+
+    // Species
+    pub species_count: usize,
+    pub species_sizes: Vec<usize>,
+
+    // Genome size
+    pub mean_nodes: f32,
+    pub mean_links: f32,
+
+    pub champion_id: u64,
+
+    // Time spent on this generation and on the whole run so far
+    pub elapsed: Duration,
+    pub total_elapsed: Duration,
+}
+
+impl GenerationStats {
+    pub const CSV_HEADER: &'static str = "generation,best_fitness,mean_fitness,median_fitness,std_fitness,species_count,species_sizes,mean_nodes,mean_links,champion_id,elapsed_secs,total_elapsed_secs";
+
+    pub fn collect(generation: u32, pop: &[Network], species_list: &[Vec<usize>], elapsed: Duration, total_elapsed: Duration) -> Self {
+        let count = pop.len().max(1) as f32;
+
+        let mut fitnesses: Vec<f32> = pop.iter().map(|n| n.fitness).collect();
+        fitnesses.sort_by(|a, b| compare_scores(*a, *b));
+
+        let mean_fitness = fitnesses.iter().sum::<f32>() / count;
+        let variance = fitnesses.iter().map(|f| (f - mean_fitness).powf(2.0)).sum::<f32>() / count;
+
+        let median_fitness = if fitnesses.len() == 0 {
+            0.0
+        } else if fitnesses.len() % 2 == 0 {
+            (fitnesses[fitnesses.len() / 2 - 1] + fitnesses[fitnesses.len() / 2]) / 2.0
+        } else {
+            fitnesses[fitnesses.len() / 2]
+        };
+
+        // The population may be ranked by novelty, so find the fittest directly
+        let champion = pop.iter().min_by(|a, b| compare_scores(b.fitness, a.fitness));
+
+        GenerationStats {
+            generation,
+
+            best_fitness: champion.map_or(0.0, |n| n.fitness),
+            mean_fitness,
+            median_fitness,
+            std_fitness: variance.sqrt(),
+
+            species_count: species_list.len(),
+            species_sizes: species_list.iter().map(|s| s.len()).collect(),
+
+            mean_nodes: pop.iter().map(|n| n.nodes.len() as f32).sum::<f32>() / count,
+            mean_links: pop.iter().map(|n| n.links.len() as f32).sum::<f32>() / count,
+
+            champion_id: champion.map_or(0, |n| n.id),
+
+            elapsed,
+            total_elapsed,
+        }
+    }
+
+    pub fn to_csv_row(&self) -> String {
+        let sizes: Vec<String> = self.species_sizes.iter().map(|s| s.to_string()).collect();
+
+        format!("{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.generation,
+            self.best_fitness,
+            self.mean_fitness,
+            self.median_fitness,
+            self.std_fitness,
+            self.species_count,
+            sizes.join(";"),
+            self.mean_nodes,
+            self.mean_links,
+            self.champion_id,
+            self.elapsed.as_secs_f64(),
+            self.total_elapsed.as_secs_f64(),
+        )
+    }
+
+    // Appends this record to a CSV file, writing the header if the file is new
+    pub fn append_csv(&self, path: &str) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", GenerationStats::CSV_HEADER)?;
+        }
+
+        writeln!(file, "{}", self.to_csv_row())
+    }
+}
+
+impl Display for GenerationStats {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Gen {}: best {:.4} mean {:.4} median {:.4} std {:.4} | {} species | {:.1} nodes {:.1} links | {:.3}s",
+            self.generation,
+            self.best_fitness,
+            self.mean_fitness,
+            self.median_fitness,
+            self.std_fitness,
+            self.species_count,
+            self.mean_nodes,
+            self.mean_links,
+            self.elapsed.as_secs_f64(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nan_fitness_does_not_abort_collection() {
+        let fitnesses = [0.5, std::f32::NAN, 0.25, 1.0];
+        let pop: Vec<Network> = fitnesses.iter().map(|&f| {
+            let mut n = Network::new(2, 1);
+            n.fitness = f;
+            n
+        }).collect();
+
+        let stats = GenerationStats::collect(0, &pop, &[vec!(0, 1, 2, 3)], Duration::from_secs(0), Duration::from_secs(0));
+        // NaN sorts below every number
+        assert_eq!(stats.median_fitness, 0.375);
+        assert_eq!(stats.best_fitness, 1.0);
+    }
+
+    #[test]
+    fn training_appends_a_csv_row_per_generation() {
+        let path = std::env::temp_dir().join(format!("neat-stats-{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut neat = crate::neat::NEAT::new(30, 3, 1);
+        neat.stats_csv_path = Some(path.to_string_lossy().to_string());
+        for _ in 0..3 {
+            neat.train();
+        }
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], GenerationStats::CSV_HEADER);
+        for (i, (line, stats)) in lines[1..].iter().zip(&neat.stats).enumerate() {
+            assert_eq!(*line, stats.to_csv_row());
+            assert!(line.starts_with(format!("{},", i).as_str()));
+            assert_eq!(line.split(',').count(), GenerationStats::CSV_HEADER.split(',').count());
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use sdl2::render::WindowCanvas;
+
+use crate::cartpole::*;
+use crate::environment::*;
+use crate::flappy::*;
+use crate::neat::*;
+use crate::network::*;
+
+// Built-in problems selectable from the command line
+#[derive(Debug, Clone, Copy)]
+pub enum Task {
+    Xor,
+    CartPole(CartPoleConfig),
+    Flappy,
+}
+
+impl Task {
+    pub const NAMES: &'static [&'static str] = &["xor", "cartpole", "cartpole-nv", "double-cartpole", "double-cartpole-nv", "flappy"];
+
+    pub fn from_name(name: &str) -> Option<Task> {
+        match name {
+            "xor" => Some(Task::Xor),
+            "cartpole" => Some(Task::CartPole(CartPoleConfig::single_pole(true))),
+            "cartpole-nv" => Some(Task::CartPole(CartPoleConfig::single_pole(false))),
+            "double-cartpole" => Some(Task::CartPole(CartPoleConfig::double_pole(true))),
+            "double-cartpole-nv" => Some(Task::CartPole(CartPoleConfig::double_pole(false))),
+            "flappy" => Some(Task::Flappy),
+            _ => None,
+        }
+    }
+
+    // Includes the bias input
+    pub fn input_count(&self) -> u32 {
+        match self {
+            Task::Xor => 3,
+            Task::CartPole(config) => config.input_count(),
+            Task::Flappy => Flappy::INPUT_COUNT,
+        }
+    }
+
+    pub fn output_count(&self) -> u32 {
+        1
+    }
+
+    pub fn fitness(&self, n: &Network) -> f32 {
+        match self {
+            Task::Xor => evaluate_xor(n),
+            Task::CartPole(config) => evaluate_cartpole(n, *config),
+            Task::Flappy => evaluate_flappy(n),
+        }
+    }
+
+    // Fitness along with a behaviour descriptor for novelty search, from a single evaluation
+    pub fn evaluate_with_behavior(&self, n: &Network) -> (f32, Vec<f32>) {
+        match self {
+            // What the network answers for each XOR case
+            Task::Xor => {
+                let outputs = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]].iter()
+                    .map(|case| n.evaluate(vec!(case[0], case[1], 1.0))[0])
+                    .collect();
+                (evaluate_xor(n), outputs)
+            },
+            Task::CartPole(config) => evaluate_cartpole_behavior(n, *config),
+            Task::Flappy => evaluate_flappy_behavior(n),
+        }
+    }
+
+    // A fresh simulation the champion can be shown playing, if the task has one
+    pub fn replay(&self) -> Option<Replay> {
+        match self {
+            Task::Xor => None,
+            Task::CartPole(config) => Some(Replay::CartPole(CartPole::new(*config))),
+            Task::Flappy => Some(Replay::Flappy(Flappy::new(1))),
+        }
+    }
+}
+
+// A task simulation stepped one frame at a time for display
+#[derive(Debug, Clone)]
+pub enum Replay {
+    CartPole(CartPole),
+    Flappy(Flappy),
+}
+
+impl Replay {
+    pub fn step(&mut self, n: &Network) {
+        let outputs = n.evaluate(self.observe());
+        self.apply(&outputs);
+    }
+
+    // Network inputs for the current state
+    pub fn observe(&self) -> Vec<f32> {
+        match self {
+            Replay::CartPole(cartpole) => cartpole.observe(),
+            Replay::Flappy(flappy) => flappy.observe(),
+        }
+    }
+
+    // Advances the simulation using the given network outputs
+    pub fn apply(&mut self, outputs: &[f32]) {
+        match self {
+            Replay::CartPole(cartpole) => {
+                Environment::step(cartpole, outputs);
+            },
+            Replay::Flappy(flappy) => {
+                flappy.step(outputs);
+            },
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        match self {
+            Replay::CartPole(cartpole) => cartpole.done(),
+            Replay::Flappy(flappy) => flappy.done(),
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut WindowCanvas, x: f32, y: f32, width: f32, height: f32) {
+        match self {
+            Replay::CartPole(cartpole) => cartpole.draw(canvas, x, y, width, height),
+            Replay::Flappy(flappy) => flappy.draw(canvas, x, y, width, height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn behaviour_evaluation_keeps_the_fitness() {
+        for name in Task::NAMES {
+            let task = Task::from_name(name).unwrap();
+            let mut n = Network::new(task.input_count(), task.output_count());
+            n.add_link(&mut 0, 0, task.input_count() as usize, 1.5).unwrap();
+
+            let (fitness, behavior) = task.evaluate_with_behavior(&n);
+            assert_eq!(fitness, task.fitness(&n));
+            assert_eq!(behavior.len(), if let Task::Xor = task { 4 } else { 2 });
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::env;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{TextureCreator, WindowCanvas};
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::WindowContext;
+
+// Used when NEAT_FONT is not set
+pub const DEFAULT_FONT_PATH: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+pub const FONT_SIZE: u16 = 14;
+
+pub fn font_path() -> String {
+    env::var("NEAT_FONT").unwrap_or(DEFAULT_FONT_PATH.to_string())
+}
+
+pub struct TextRenderer<'ttf> {
+    font: Font<'ttf, 'static>,
+    texture_creator: TextureCreator<WindowContext>,
+}
+
+impl<'ttf> TextRenderer<'ttf> {
+    pub fn new(ttf_context: &'ttf Sdl2TtfContext, canvas: &WindowCanvas, path: &str, point_size: u16) -> Result<Self, String> {
+        let font = ttf_context.load_font(path, point_size)?;
+
+        Ok(TextRenderer {
+            font,
+            texture_creator: canvas.texture_creator(),
+        })
+    }
+
+    pub fn line_height(&self) -> i32 {
+        self.font.recommended_line_spacing()
+    }
+
+    // Draws a single line of text with its top left corner at (x, y)
+    pub fn draw(&self, canvas: &mut WindowCanvas, text: &str, x: i32, y: i32, color: Color) {
+        // SDL_ttf refuses to render zero width text
+        if text.is_empty() {
+            return;
+        }
+
+        let surface = self.font.render(text).blended(color)
+            .expect("Failed to render text");
+        let texture = self.texture_creator.create_texture_from_surface(&surface)
+            .expect("Failed to create text texture");
+
+        canvas.copy(&texture, None, Rect::new(x, y, surface.width(), surface.height()))
+            .expect("Failed to draw text");
+    }
+}
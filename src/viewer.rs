@@ -0,0 +1,208 @@
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::render::WindowCanvas;
+
+use crate::network::*;
+use crate::neat::*;
+use crate::text::TextRenderer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewMode {
+    // One network with the fitness graph
+    Network,
+    // A tile for every species champion
+    SpeciesGrid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    // Browse every genome in the population
+    Genome,
+    // Browse the best genome of each species
+    SpeciesChampion,
+}
+
+// Keyboard state for the SDL window
+//   Space        pause / resume training
+//   N            step one generation while paused
+//   Left / Right previous / next genome
+//   Up / Down    previous / next species champion
+//   S            save the displayed genome
+//   W            show / hide link weights
+//   G            switch between the network view and the species grid
+#[derive(Debug, Clone)]
+pub struct Viewer {
+    pub view: ViewMode,
+    pub paused: bool,
+    pub step_requested: bool,
+    pub selection: Selection,
+    pub index: usize,
+    pub show_weights: bool,
+}
+
+impl Viewer {
+    pub fn new() -> Self {
+        Viewer {
+            view: ViewMode::Network,
+            paused: false,
+            step_requested: false,
+            selection: Selection::Genome,
+            index: 0,
+            show_weights: false,
+        }
+    }
+
+    pub fn handle_key(&mut self, keycode: Keycode, neat: &NEAT) {
+        match keycode {
+            Keycode::Space => self.paused = !self.paused,
+            Keycode::N => self.step_requested = true,
+            Keycode::W => self.show_weights = !self.show_weights,
+            Keycode::G => {
+                self.view = match self.view {
+                    ViewMode::Network => ViewMode::SpeciesGrid,
+                    ViewMode::SpeciesGrid => ViewMode::Network,
+                };
+            },
+            Keycode::Left => self.cycle(Selection::Genome, neat, false),
+            Keycode::Right => self.cycle(Selection::Genome, neat, true),
+            Keycode::Up => self.cycle(Selection::SpeciesChampion, neat, false),
+            Keycode::Down => self.cycle(Selection::SpeciesChampion, neat, true),
+            Keycode::S => {
+                let network = self.displayed(neat);
+                let path = format!("genome_{}.txt", network.id);
+                match network.save(path.as_str()) {
+                    Ok(()) => println!("Saved genome {} to {}", network.id, path),
+                    Err(e) => eprintln!("Failed to save genome to {}: {}", path, e),
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // Returns whether a generation should be trained this frame
+    pub fn should_train(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+
+        let step = self.step_requested;
+        self.step_requested = false;
+        step
+    }
+
+    // Species champions are the evaluated networks, not their mutated offspring
+    pub fn displayed<'a>(&self, neat: &'a NEAT) -> &'a Network {
+        let champion = match self.selection {
+            Selection::Genome => None,
+            Selection::SpeciesChampion => neat.species_info.get(self.index).and_then(|species| species.champion.as_ref()),
+        };
+
+        match champion {
+            Some(champion) => champion,
+            None => &neat.pop[self.index.min(neat.pop.len() - 1)],
+        }
+    }
+
+    pub fn draw_hud(&self, canvas: &mut WindowCanvas, text: &TextRenderer, neat: &NEAT, x: i32, y: i32) {
+        let network = self.displayed(neat);
+        let best_fitness = neat.stats.last().map_or(0.0, |s| s.best_fitness);
+        let enabled_links = network.links.iter().filter(|l| l.enabled).count();
+
+        let selection_str = match self.selection {
+            Selection::Genome => format!("Genome {} / {}", self.index + 1, neat.pop.len()),
+            Selection::SpeciesChampion => format!("Species champion {} / {}", self.index + 1, neat.species_list.len()),
+        };
+
+        let mut lines: Vec<String> = vec!(
+            format!("Generation: {}", neat.generation),
+            format!("Best fitness: {:.4}", best_fitness),
+            format!("Species: {}", neat.species_list.len()),
+            format!("{} (id {})", selection_str, network.id),
+            format!("Size: {} nodes, {} links ({} enabled)", network.nodes.len(), network.links.len(), enabled_links),
+        );
+        if self.paused {
+            lines.push("PAUSED".to_string());
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            text.draw(canvas, line.as_str(), x, y + i as i32 * text.line_height(), Color::RGB(255, 255, 255));
+        }
+    }
+
+    // Tiles the area with each species champion, outlined in its species colour
+    pub fn draw_species_grid(&self, canvas: &mut WindowCanvas, text: Option<&TextRenderer>, neat: &NEAT, x: f32, y: f32, width: f32, height: f32) {
+        let species_count = neat.species_list.len();
+        if species_count == 0 {
+            return;
+        }
+
+        let columns = (species_count as f32).sqrt().ceil() as usize;
+        let rows = (species_count + columns - 1) / columns;
+        let tile_width = width / columns as f32;
+        let tile_height = height / rows as f32;
+
+        for i in 0..species_count {
+            let species = &neat.species_list[i];
+            let info = &neat.species_info[i];
+            let champion = match &info.champion {
+                Some(champion) => champion,
+                None => continue,
+            };
+
+            let tile_x = x + (i % columns) as f32 * tile_width;
+            let tile_y = y + (i / columns) as f32 * tile_height;
+            let color = species_color(info.id);
+
+            canvas.rectangle(tile_x as i16 + 2, tile_y as i16 + 2, (tile_x + tile_width) as i16 - 2, (tile_y + tile_height) as i16 - 2, color)
+                .expect("Failed to draw species tile");
+
+            champion.draw(canvas, tile_x + 20.0, tile_y + 60.0, tile_width - 40.0, tile_height - 100.0);
+
+            if let Some(text) = text {
+                let label = format!("Species {} | size {} | age {} | best {:.3}", info.id, species.len(), neat.generation - info.created, info.best_fitness);
+                text.draw(canvas, label.as_str(), tile_x as i32 + 8, (tile_y + tile_height) as i32 - 8 - text.line_height(), Color::RGBA(color.0, color.1, color.2, color.3));
+            }
+        }
+    }
+
+    fn cycle(&mut self, selection: Selection, neat: &NEAT, forward: bool) {
+        let count = match selection {
+            Selection::Genome => neat.pop.len(),
+            Selection::SpeciesChampion => neat.species_list.len(),
+        };
+
+        if count == 0 {
+            return;
+        }
+
+        if self.selection != selection {
+            self.selection = selection;
+            self.index = 0;
+        } else if forward {
+            self.index = (self.index + 1) % count;
+        } else {
+            self.index = (self.index + count - 1) % count;
+        }
+    }
+}
+
+// Spreads species ids around the hue wheel so each keeps the same colour across generations
+pub fn species_color(id: u32) -> (u8, u8, u8, u8) {
+    let hue = (id as f32 * 0.618_034).fract() * 6.0;
+    let falling = 1.0 - hue.fract();
+    let rising = hue.fract();
+
+    let (r, g, b) = match hue as u32 {
+        0 => (1.0, rising, 0.0),
+        1 => (falling, 1.0, 0.0),
+        2 => (0.0, 1.0, rising),
+        3 => (0.0, falling, 1.0),
+        4 => (rising, 0.0, 1.0),
+        _ => (1.0, 0.0, falling),
+    };
+
+    // Keep colours bright enough to read on black
+    let scale = |c: f32| (100.0 + c * 155.0) as u8;
+    (scale(r), scale(g), scale(b), 255)
+}